@@ -0,0 +1,290 @@
+use std::collections::HashSet;
+
+use super::{
+  json::{JsonMap, JsonValue},
+  Error,
+};
+
+/// Options for [`CSV::parse_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvOptions {
+  /// The character between fields, `,` by default. Use `;` or `\t` for the
+  /// other common dialects.
+  pub delimiter: char,
+}
+
+impl Default for CsvOptions {
+  fn default() -> Self { Self { delimiter: ',' } }
+}
+
+/// Reader over a borrowed CSV string. `index` is a byte offset into `csv`
+/// that always sits on a char boundary.
+struct CsvParser<'a> {
+  csv:     &'a str,
+  options: CsvOptions,
+  index:   usize,
+}
+
+impl<'a> CsvParser<'a> {
+  pub fn new(csv: &'a str, options: CsvOptions) -> Self {
+    Self {
+      csv,
+      options,
+      index: 0,
+    }
+  }
+
+  fn current(&self) -> Option<char> { self.csv[self.index..].chars().next() }
+
+  fn advance(&mut self) -> Option<char> {
+    if let Some(c) = self.current() {
+      self.index += c.len_utf8();
+    }
+    self.current()
+  }
+
+  /// Consumes a line break (`\n`, `\r\n` or a lone `\r`) if there is one.
+  fn line_break(&mut self) -> bool {
+    match self.current() {
+      Some('\n') => {
+        self.advance();
+        true
+      },
+      Some('\r') => {
+        if self.advance() == Some('\n') {
+          self.advance();
+        }
+        true
+      },
+      _ => false,
+    }
+  }
+
+  fn parse_quoted_field(&mut self) -> Result<String, Error> {
+    let start = self.index;
+    let mut result = String::new();
+    loop {
+      match self.advance() {
+        Some('"') => {
+          if self.advance() != Some('"') {
+            break;
+          }
+          result.push('"');
+        },
+        Some(c) => result.push(c),
+        None => return Err(Error::csv("Unterminated quoted field".to_string(), start)),
+      }
+    }
+    match self.current() {
+      None | Some('\r' | '\n') => Ok(result),
+      Some(c) if c == self.options.delimiter => Ok(result),
+      Some(c) => Err(Error::csv(
+        format!("Unexpected '{}' after closing quote", c),
+        self.index,
+      )),
+    }
+  }
+
+  fn parse_field(&mut self) -> Result<String, Error> {
+    if self.current() == Some('"') {
+      return self.parse_quoted_field();
+    }
+    let start = self.index;
+    while let Some(c) = self.current() {
+      if c == self.options.delimiter || c == '\r' || c == '\n' {
+        break;
+      }
+      self.advance();
+    }
+    Ok(self.csv[start..self.index].to_string())
+  }
+
+  fn parse_record(&mut self) -> Result<Vec<String>, Error> {
+    let mut record = vec![self.parse_field()?];
+    while self.current() == Some(self.options.delimiter) {
+      self.advance();
+      record.push(self.parse_field()?);
+    }
+    Ok(record)
+  }
+
+  pub fn parse(&mut self) -> Result<Vec<Vec<String>>, Error> {
+    let records = self.parse_document()?;
+    Ok(records.into_iter().map(|(_, record)| record).collect())
+  }
+
+  /// Parses the records with the first one as a header row, turning every
+  /// other record into an object keyed by the header's fields.
+  pub fn parse_records(&mut self) -> Result<JsonValue, Error> {
+    let mut records = self.parse_document()?.into_iter();
+    let Some((_, header)) = records.next() else {
+      return Ok(JsonValue::Array(vec![]));
+    };
+    let mut result = vec![];
+    for (row, (start, record)) in records.enumerate() {
+      if record.len() != header.len() {
+        let message = format!(
+          "Row {} has {} fields, expected {}",
+          row + 2,
+          record.len(),
+          header.len()
+        );
+        return Err(Error::csv(message, start).locate(self.csv));
+      }
+      let object: JsonMap = header
+        .iter()
+        .cloned()
+        .zip(record.into_iter().map(JsonValue::String))
+        .collect();
+      result.push(JsonValue::Object(object));
+    }
+    Ok(JsonValue::Array(result))
+  }
+
+  /// Parses every record along with the byte offset where it starts.
+  fn parse_document(&mut self) -> Result<Vec<(usize, Vec<String>)>, Error> {
+    let mut records = vec![];
+    while self.current().is_some() {
+      let start = self.index;
+      let record = self
+        .parse_record()
+        .map_err(|error| error.locate(self.csv))?;
+      records.push((start, record));
+      self.line_break();
+    }
+    Ok(records)
+  }
+}
+
+/// Appends `field` to `out`, quoted if it contains the delimiter, a quote
+/// or a line break.
+fn write_csv_field(field: &str, delimiter: char, out: &mut String) {
+  if field.contains([delimiter, '"', '\n', '\r']) {
+    out.push('"');
+    out.push_str(&field.replace('"', "\"\""));
+    out.push('"');
+  } else {
+    out.push_str(field);
+  }
+}
+
+/// Appends one record of `fields` to `out`, ending it with `\n`.
+fn write_csv_record(
+  fields: impl Iterator<Item = impl AsRef<str>>,
+  delimiter: char,
+  out: &mut String,
+) {
+  for (i, field) in fields.enumerate() {
+    if i > 0 {
+      out.push(delimiter);
+    }
+    write_csv_field(field.as_ref(), delimiter, out);
+  }
+  out.push('\n');
+}
+
+/// Struct with methods for parsing CSV (RFC 4180) text.
+pub struct CSV {}
+
+impl CSV {
+  /// Parses a CSV string into records of fields.
+  ///
+  /// Fields may be quoted with `"`, in which case they can contain the
+  /// delimiter, line breaks and `""` for a literal quote. Records end at `\n`,
+  /// `\r\n` or `\r`; a line break after the last record is optional.
+  ///
+  /// # Arguments
+  ///
+  /// - `csv` - The CSV string to parse.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if a quoted field is never closed or is followed by
+  /// anything but a delimiter or line break.
+  pub fn parse(csv: &str) -> Result<Vec<Vec<String>>, Error> {
+    CSV::parse_with(csv, CsvOptions::default())
+  }
+
+  /// Parses a CSV string like [`CSV::parse`], but with custom options.
+  ///
+  /// # Arguments
+  ///
+  /// - `csv` - The CSV string to parse.
+  /// - `options` - The options describing the CSV dialect.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if the CSV string is invalid.
+  pub fn parse_with(csv: &str, options: CsvOptions) -> Result<Vec<Vec<String>>, Error> {
+    CsvParser::new(csv, options).parse()
+  }
+
+  /// Parses a CSV string whose first record is a header row into a
+  /// [`JsonValue::Array`] with one [`JsonValue::Object`] per remaining record,
+  /// keyed by the header names. All field values are strings.
+  ///
+  /// # Arguments
+  ///
+  /// - `csv` - The CSV string to parse.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if the CSV string is invalid, or if a row has a
+  /// different number of fields than the header. Rows are numbered from 1,
+  /// counting the header.
+  pub fn parse_records(csv: &str) -> Result<JsonValue, Error> {
+    CsvParser::new(csv, CsvOptions::default()).parse_records()
+  }
+
+  /// Serializes a [`JsonValue::Array`] of objects into CSV, the inverse of
+  /// [`CSV::parse_records`]. The header row holds every key of every object
+  /// in the order they are first seen, and each object becomes one record.
+  /// A key an object lacks, or a null, becomes an empty field. Strings are
+  /// written as is, other values as JSON. Every record ends with `\n`.
+  ///
+  /// # Arguments
+  ///
+  /// - `value` - The array of objects to serialize.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if `value` is not an array, or if one of its elements
+  /// is not an object, in which case the error's index is the element's.
+  pub fn stringify(value: &JsonValue) -> Result<String, Error> {
+    let Some(rows) = value.as_array() else {
+      return Err(Error::csv(
+        "Only an array of objects can be written as CSV".to_string(),
+        0,
+      ));
+    };
+    let mut header: Vec<&str> = vec![];
+    let mut seen = HashSet::new();
+    for (i, row) in rows.iter().enumerate() {
+      let Some(row) = row.as_object() else {
+        return Err(Error::csv(format!("Element {} is not an object", i), i));
+      };
+      header.extend(
+        row
+          .keys()
+          .map(String::as_str)
+          .filter(|&key| seen.insert(key)),
+      );
+    }
+    let delimiter = CsvOptions::default().delimiter;
+    let mut out = String::new();
+    if header.is_empty() {
+      return Ok(out);
+    }
+    write_csv_record(header.iter().copied(), delimiter, &mut out);
+    for row in rows {
+      let row = row.as_object().unwrap();
+      let fields = header.iter().map(|&key| match row.get(key) {
+        None | Some(JsonValue::Null) => String::new(),
+        Some(JsonValue::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+      });
+      write_csv_record(fields, delimiter, &mut out);
+    }
+    Ok(out)
+  }
+}
@@ -1,43 +1,233 @@
-use std::fmt::{self, Display, Formatter};
+use std::{
+  fmt::{self, Display, Formatter},
+  io,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum FileType {
   JSON,
+  CSV,
+  TOML,
+  INI,
+  QueryString,
+  PercentEncoding,
+  XML,
+  YAML,
 }
 
 impl Display for FileType {
   fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
     match self {
       FileType::JSON => write!(f, "JSON"),
+      FileType::CSV => write!(f, "CSV"),
+      FileType::TOML => write!(f, "TOML"),
+      FileType::INI => write!(f, "INI"),
+      FileType::QueryString => write!(f, "query string"),
+      FileType::PercentEncoding => write!(f, "percent-encoded text"),
+      FileType::XML => write!(f, "XML"),
+      FileType::YAML => write!(f, "YAML"),
     }
   }
 }
 
+/// What went wrong, as opposed to where. The JSON parser reports the
+/// specific kinds below; other file types, and failures no specific kind
+/// covers, report [`ErrorKind::Syntax`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+  /// The input is not valid for its file type, in a way no more specific
+  /// kind describes.
+  Syntax,
+  /// A character that cannot start or continue any token.
+  UnexpectedChar,
+  /// A well-formed token where it is not allowed, or a misspelled keyword.
+  UnexpectedToken,
+  /// A string that is missing its closing quote.
+  UnterminatedString,
+  /// A malformed number literal.
+  InvalidNumber,
+  /// A malformed escape sequence inside a string.
+  InvalidEscape,
+  /// An object key that appears more than once.
+  DuplicateKey,
+  /// More input after a complete document.
+  TrailingContent,
+  /// The input ends in the middle of a value.
+  UnexpectedEof,
+  /// Arrays and objects nested deeper than allowed.
+  DepthExceeded,
+  /// A string or document larger than allowed.
+  LimitExceeded,
+  /// The input is not valid UTF-8, so it is not text at all.
+  Encoding,
+  /// The input could not be read. The [`io::Error`] itself is the error's
+  /// [`source`](std::error::Error::source).
+  Io(io::ErrorKind),
+}
+
+impl ErrorKind {
+  /// Whether the input was read and decoded but is malformed, as opposed to
+  /// an [`ErrorKind::Io`] or [`ErrorKind::Encoding`] failure.
+  pub fn is_syntax(self) -> bool { !matches!(self, ErrorKind::Io(_) | ErrorKind::Encoding) }
+}
+
 #[derive(Debug)]
 pub struct Error {
-  pub filetype: FileType,
-  pub message:  String,
-  pub index:    usize,
+  pub filetype:      FileType,
+  pub kind:          ErrorKind,
+  pub message:       String,
+  /// Byte offset into the source. Errors that do not come from source text
+  /// use it for the position of the offending item instead.
+  pub index:         usize,
+  /// 1-based line of `index` in the source, or 0 if it is not known yet.
+  pub line:          usize,
+  /// 1-based column (in characters) of `index` in the source, or 0 if it is
+  /// not known yet.
+  pub column:        usize,
+  /// The I/O error behind an [`ErrorKind::Io`] error, returned by
+  /// [`std::error::Error::source`].
+  pub(crate) source: Option<io::Error>,
 }
 
 impl Error {
   pub fn new(filetype: FileType, message: String, index: usize) -> Self {
     Self {
       filetype,
+      kind: ErrorKind::Syntax,
       message,
       index,
+      line: 0,
+      column: 0,
+      source: None,
     }
   }
 
   pub fn json(message: String, index: usize) -> Self { Self::new(FileType::JSON, message, index) }
+
+  pub fn csv(message: String, index: usize) -> Self { Self::new(FileType::CSV, message, index) }
+
+  pub fn toml(message: String, index: usize) -> Self { Self::new(FileType::TOML, message, index) }
+
+  pub fn ini(message: String, index: usize) -> Self { Self::new(FileType::INI, message, index) }
+
+  pub fn query_string(message: String, index: usize) -> Self {
+    Self::new(FileType::QueryString, message, index)
+  }
+
+  pub fn percent(message: String, index: usize) -> Self {
+    Self::new(FileType::PercentEncoding, message, index)
+  }
+
+  pub fn xml(message: String, index: usize) -> Self { Self::new(FileType::XML, message, index) }
+
+  pub fn yaml(message: String, index: usize) -> Self { Self::new(FileType::YAML, message, index) }
+
+  /// Replaces the kind of this error, which is [`ErrorKind::Syntax`] when
+  /// created by the constructors above.
+  pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+    self.kind = kind;
+    self
+  }
+
+  /// An error for input of `filetype` that failed to be read at all. It
+  /// keeps `error` as its source.
+  pub fn io(filetype: FileType, error: io::Error) -> Self {
+    let message = error.to_string();
+    Self {
+      kind: ErrorKind::Io(error.kind()),
+      source: Some(error),
+      ..Self::new(filetype, message, 0)
+    }
+  }
+
+  /// Resolves `index` into a line and column of `source`. A `\r\n` pair
+  /// counts as a single line break.
+  pub(crate) fn locate(mut self, source: &str) -> Self {
+    (self.line, self.column) = position(source, self.index);
+    self
+  }
+
+  /// Renders this error followed by the line of `source` it points into, with
+  /// a caret under the position, in the style of compiler diagnostics.
+  /// `source` must be the input the error came from. Errors of kind
+  /// [`ErrorKind::Io`] have no position and render like [`Display`].
+  pub fn with_context(&self, source: &str) -> String {
+    if let ErrorKind::Io(_) = self.kind {
+      return self.to_string();
+    }
+    let mut index = self.index.min(source.len());
+    while !source.is_char_boundary(index) {
+      index -= 1;
+    }
+    let (line, _) = position(source, index);
+    let start = source[..index].rfind(['\n', '\r']).map_or(0, |i| i + 1);
+    let end = source[index..]
+      .find(['\n', '\r'])
+      .map_or(source.len(), |i| index + i);
+    // Keep tabs so the caret lines up however wide they are shown, and leave
+    // out a byte order mark, which is not shown at all.
+    let pad: String = source[start..index]
+      .chars()
+      .filter(|&c| c != '\u{feff}')
+      .map(|c| if c == '\t' { '\t' } else { ' ' })
+      .collect();
+    let width = line.to_string().len();
+    format!(
+      "{}\n{:width$} |\n{} | {}\n{:width$} | {}^",
+      self,
+      "",
+      line,
+      &source[start..end],
+      "",
+      pad,
+    )
+  }
+}
+
+/// The 1-based line and column (in characters) of byte offset `index` in
+/// `source`.
+fn position(source: &str, index: usize) -> (usize, usize) {
+  let (mut line, mut column) = (1, 1);
+  let mut chars = source.char_indices().peekable();
+  while let Some((i, c)) = chars.next() {
+    if i >= index {
+      break;
+    }
+    match c {
+      '\n' => (line, column) = (line + 1, 1),
+      '\r' if chars.peek().map(|&(_, c)| c) != Some('\n') => (line, column) = (line + 1, 1),
+      '\r' => (),
+      // A byte order mark is not shown, so it takes up no column.
+      '\u{feff}' if i == 0 => (),
+      _ => column += 1,
+    }
+  }
+  (line, column)
 }
 
 impl Display for Error {
   fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+    if let ErrorKind::Io(_) = self.kind {
+      return write!(f, "ERROR: {} while reading {}", self.message, self.filetype);
+    }
+    if self.line == 0 {
+      return write!(
+        f,
+        "ERROR: {} in {} at index {}",
+        self.message, self.filetype, self.index
+      );
+    }
     write!(
       f,
-      "ERROR: {} in {} at index {}",
-      self.message, self.filetype, self.index
+      "ERROR: {} in {} at line {}, column {}",
+      self.message, self.filetype, self.line, self.column
     )
   }
 }
+
+impl std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    self.source.as_ref().map(|error| error as _)
+  }
+}
@@ -0,0 +1,135 @@
+use super::{
+  json::{JsonMap, JsonValue},
+  Error,
+};
+
+/// Parses a quoted value starting at `value`, which sits at byte offset
+/// `start` of the input. Double quotes support backslash escapes, single
+/// quotes take their contents literally. Only a comment may follow.
+fn parse_quoted(value: &str, start: usize) -> Result<String, Error> {
+  let quote = value.chars().next().unwrap();
+  let mut result = String::new();
+  let mut chars = value.char_indices().skip(1);
+  while let Some((i, c)) = chars.next() {
+    match c {
+      '\\' if quote == '"' => match chars.next() {
+        Some((_, 'n')) => result.push('\n'),
+        Some((_, 't')) => result.push('\t'),
+        Some((_, 'r')) => result.push('\r'),
+        Some((_, c @ ('"' | '\\'))) => result.push(c),
+        _ => return Err(Error::ini("Invalid escape sequence".to_string(), start + i)),
+      },
+      c if c == quote => {
+        let rest = value[i + 1..].trim_start();
+        if !rest.is_empty() && !rest.starts_with(['#', ';']) {
+          let pos = start + value.len() - rest.len();
+          return Err(Error::ini(
+            "Unexpected text after closing quote".to_string(),
+            pos,
+          ));
+        }
+        return Ok(result);
+      },
+      c => result.push(c),
+    }
+  }
+  Err(Error::ini("Unterminated quoted value".to_string(), start))
+}
+
+fn parse_document(input: &str) -> Result<JsonValue, Error> {
+  let mut root = JsonMap::new();
+  let mut section: Option<String> = None;
+  let mut offset = 0;
+  for line in input.split_inclusive('\n') {
+    let start = offset;
+    offset += line.len();
+    let trimmed = line.trim();
+    let pos = start + line.len() - line.trim_start().len();
+    if trimmed.is_empty() || trimmed.starts_with(['#', ';']) {
+      continue;
+    }
+    if let Some(header) = trimmed.strip_prefix('[') {
+      let Some((name, rest)) = header.split_once(']') else {
+        return Err(Error::ini("Unterminated section header".to_string(), pos));
+      };
+      let rest = rest.trim_start();
+      if !rest.is_empty() && !rest.starts_with(['#', ';']) {
+        return Err(Error::ini(
+          "Unexpected text after section header".to_string(),
+          pos + trimmed.len() - rest.len(),
+        ));
+      }
+      let name = name.trim();
+      if name.is_empty() {
+        return Err(Error::ini("Empty section name".to_string(), pos));
+      }
+      match root.get(name) {
+        Some(JsonValue::Object(_)) => (),
+        Some(_) => return Err(Error::ini(format!("Key '{}' is not a section", name), pos)),
+        None => {
+          root.insert(name.to_string(), JsonValue::Object(JsonMap::new()));
+        },
+      }
+      section = Some(name.to_string());
+      continue;
+    }
+    let entry = trimmed.strip_prefix("export ").unwrap_or(trimmed);
+    let Some((key, value)) = entry.split_once('=') else {
+      return Err(Error::ini("Expected '=' after key".to_string(), pos));
+    };
+    let key = key.trim();
+    if key.is_empty() {
+      return Err(Error::ini("Missing key".to_string(), pos));
+    }
+    let value = value.trim();
+    let value = match value.starts_with(['"', '\'']) {
+      // `value` is a slice of `line`, so its offset follows from the pointers.
+      true => parse_quoted(
+        value,
+        start + (value.as_ptr() as usize - line.as_ptr() as usize),
+      )?,
+      false => value.to_string(),
+    };
+    let table = match &section {
+      Some(name) => match root.get_mut(name) {
+        Some(JsonValue::Object(table)) => table,
+        _ => unreachable!(),
+      },
+      None => &mut root,
+    };
+    table.insert(key.to_string(), JsonValue::String(value));
+  }
+  Ok(JsonValue::Object(root))
+}
+
+/// Struct with methods for parsing INI and `.env` files.
+pub struct INI {}
+
+impl INI {
+  /// Parses an INI or `.env` string into a [`JsonValue::Object`]. Keys
+  /// before the first `[section]` header go into the object itself, each
+  /// section becomes a nested object, and every value is a
+  /// [`JsonValue::String`].
+  ///
+  /// Lines starting with `#` or `;` are comments, and a comment may also
+  /// follow a section header. Keys and values are
+  /// trimmed, and a value only ends at the line break, so it may contain
+  /// `=` or `#`. A value in double quotes supports `\n`, `\t`, `\r`, `\"`
+  /// and `\\` escapes, one in single quotes is taken literally, and either
+  /// may be followed by a comment. A leading `export ` is ignored. When a
+  /// key repeats within a section, the last value wins, and a repeated
+  /// section header continues the earlier section.
+  ///
+  /// # Arguments
+  ///
+  /// - `input` - The INI string to parse.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if a line is neither a comment, a section header nor
+  /// a `key=value` pair, if a quoted value is malformed, or if a name is
+  /// used both as a top-level key and as a section.
+  pub fn parse(input: &str) -> Result<JsonValue, Error> {
+    parse_document(input).map_err(|error| error.locate(input))
+  }
+}
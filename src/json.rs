@@ -1,424 +1,3915 @@
-use std::{collections::HashMap, ops::Index};
+use std::{
+  cmp::Ordering,
+  collections::HashMap,
+  fmt::{self, Display, Formatter},
+  hash::{Hash, Hasher},
+  io::Read,
+  ops::{Index, IndexMut},
+  str::FromStr,
+};
 
-use super::Error;
+use super::{Error, ErrorKind, FileType};
 
-#[derive(Debug, PartialEq, Clone)]
+/// Builds a [`JsonValue`] from JSON-like syntax.
+///
+/// Object keys must be string literals or parenthesized expressions that
+/// convert into a `String`. Any other value is converted with
+/// `JsonValue::from`, so Rust expressions can be embedded directly.
+///
+/// ```
+/// use parsers::{json, json::JsonValue};
+///
+/// let x = 1.5;
+/// let value = json!({"a": [true, null, x], "b": "c"});
+/// assert_eq!(value["a"][2], JsonValue::Number(1.5));
+/// ```
+#[macro_export]
+macro_rules! json {
+  (null) => {
+    $crate::json::JsonValue::Null
+  };
+  ([]) => {
+    $crate::json::JsonValue::Array(::std::vec::Vec::new())
+  };
+  ([ $($tt:tt)+ ]) => {
+    $crate::json::JsonValue::Array($crate::json!(@array [] $($tt)+))
+  };
+  ({}) => {
+    $crate::json::JsonValue::Object($crate::json::JsonMap::new())
+  };
+  ({ $($tt:tt)+ }) => {{
+    let mut object = $crate::json::JsonMap::new();
+    $crate::json!(@object object $($tt)+);
+    $crate::json::JsonValue::Object(object)
+  }};
+  ($other:expr) => {
+    $crate::json::JsonValue::from($other)
+  };
+
+  (@array [$($elems:expr,)*]) => {
+    ::std::vec![$($elems,)*]
+  };
+  (@array [$($elems:expr,)*] null $(, $($rest:tt)*)?) => {
+    $crate::json!(@array [$($elems,)* $crate::json!(null),] $($($rest)*)?)
+  };
+  (@array [$($elems:expr,)*] [$($array:tt)*] $(, $($rest:tt)*)?) => {
+    $crate::json!(@array [$($elems,)* $crate::json!([$($array)*]),] $($($rest)*)?)
+  };
+  (@array [$($elems:expr,)*] {$($object:tt)*} $(, $($rest:tt)*)?) => {
+    $crate::json!(@array [$($elems,)* $crate::json!({$($object)*}),] $($($rest)*)?)
+  };
+  (@array [$($elems:expr,)*] $next:expr, $($rest:tt)*) => {
+    $crate::json!(@array [$($elems,)* $crate::json!($next),] $($rest)*)
+  };
+  (@array [$($elems:expr,)*] $last:expr) => {
+    $crate::json!(@array [$($elems,)* $crate::json!($last),])
+  };
+
+  (@object $object:ident) => {};
+  (@object $object:ident $key:tt : null $(, $($rest:tt)*)?) => {
+    $object.insert(::std::string::String::from($key), $crate::json!(null));
+    $crate::json!(@object $object $($($rest)*)?);
+  };
+  (@object $object:ident $key:tt : [$($array:tt)*] $(, $($rest:tt)*)?) => {
+    $object.insert(::std::string::String::from($key), $crate::json!([$($array)*]));
+    $crate::json!(@object $object $($($rest)*)?);
+  };
+  (@object $object:ident $key:tt : {$($inner:tt)*} $(, $($rest:tt)*)?) => {
+    $object.insert(::std::string::String::from($key), $crate::json!({$($inner)*}));
+    $crate::json!(@object $object $($($rest)*)?);
+  };
+  (@object $object:ident $key:tt : $value:expr, $($rest:tt)*) => {
+    $object.insert(::std::string::String::from($key), $crate::json!($value));
+    $crate::json!(@object $object $($rest)*);
+  };
+  (@object $object:ident $key:tt : $value:expr) => {
+    $object.insert(::std::string::String::from($key), $crate::json!($value));
+  };
+}
+
+/// A parsed JSON value.
+///
+/// Integer literals are kept as [`JsonValue::Integer`] so they do not lose
+/// precision, everything else numeric is a [`JsonValue::Number`]. The two
+/// compare equal when they hold the same numeric value.
+///
+/// `JsonValue` implements [`Drop`] so that deeply nested values are freed
+/// without recursing. Patterns therefore cannot move a string or container
+/// out of a value; match on `&mut value` and use [`std::mem::take`] instead.
+#[derive(Debug, Default)]
 pub enum JsonValue {
+  #[default]
   Null,
   String(String),
   Number(f64),
+  Integer(i64),
+  /// A number literal kept exactly as written, as produced when parsing with
+  /// [`NumberMode::Raw`], for use with arbitrary-precision libraries. It is
+  /// stringified verbatim, but compares with other numbers, raw or not, by
+  /// its numeric value, so `1.0` equals `1`.
+  RawNumber(String),
   Boolean(bool),
   Array(Vec<JsonValue>),
-  Object(HashMap<String, JsonValue>),
+  Object(JsonMap),
 }
 
-impl Index<usize> for JsonValue {
-  type Output = JsonValue;
+impl JsonValue {
+  /// Parses a JSON string, borrowed or owned, like [`JSON::parse`]. This is
+  /// the conversion to reach for where `TryFrom<&str>` would be expected;
+  /// see the [`FromStr`] impl for why there is none.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if the JSON string is invalid.
+  pub fn try_parse(s: impl AsRef<str>) -> Result<JsonValue, Error> { JSON::parse(s.as_ref()) }
 
-  fn index(&self, index: usize) -> &Self::Output {
+  /// Returns the value stored under `key` if this is an object containing
+  /// that key, or `None` otherwise.
+  pub fn get(&self, key: &str) -> Option<&JsonValue> {
     match self {
-      JsonValue::Array(arr) => &arr[index],
-      _ => panic!("not an array"),
+      JsonValue::Object(obj) => obj.get(key),
+      _ => None,
     }
   }
-}
 
-impl Index<&str> for JsonValue {
-  type Output = JsonValue;
+  /// Returns the element at index `i` if this is an array and the index is in
+  /// bounds, or `None` otherwise.
+  pub fn get_index(&self, i: usize) -> Option<&JsonValue> {
+    match self {
+      JsonValue::Array(arr) => arr.get(i),
+      _ => None,
+    }
+  }
 
-  fn index(&self, key: &str) -> &Self::Output {
+  /// Returns the element at index `i` if this is an array, where a negative
+  /// `i` counts from the end, so `at(-1)` is the last element. Returns `None`
+  /// if the index is out of bounds or this is not an array.
+  pub fn at(&self, i: isize) -> Option<&JsonValue> {
+    let len = self.as_array()?.len();
+    let i = match i {
+      i if i < 0 => len.checked_sub(i.unsigned_abs())?,
+      i => i as usize,
+    };
+    self.get_index(i)
+  }
+
+  /// Like [`JsonValue::get`], but returns a mutable reference.
+  pub fn get_mut(&mut self, key: &str) -> Option<&mut JsonValue> {
     match self {
-      JsonValue::Object(obj) => &obj[key],
-      _ => panic!("not an object"),
+      JsonValue::Object(obj) => obj.get_mut(key),
+      _ => None,
     }
   }
-}
 
-#[derive(PartialEq, Clone)]
-enum JsonToken {
-  Null { pos: usize },
-  String { val: String, pos: usize },
-  Number { val: f64, pos: usize },
-  Boolean { val: bool, pos: usize },
-  Colon { pos: usize },
-  Comma { pos: usize },
-  LeftBracket { pos: usize },
-  RightBracket { pos: usize },
-  LeftBrace { pos: usize },
-  RightBrace { pos: usize },
-  Eof { pos: usize },
-}
-
-struct JsonLexer {
-  json:  String,
-  index: usize,
-}
-
-impl JsonLexer {
-  pub fn new(json: String) -> Self {
-    Self {
-      json:  json.trim().to_string(),
-      index: 0,
+  /// Like [`JsonValue::get_index`], but returns a mutable reference.
+  pub fn get_index_mut(&mut self, i: usize) -> Option<&mut JsonValue> {
+    match self {
+      JsonValue::Array(arr) => arr.get_mut(i),
+      _ => None,
     }
   }
 
-  fn advance(&mut self) -> Option<char> {
-    self.index += 1;
-    self.json.chars().nth(self.index)
+  /// Returns the name of this value's JSON type: `"null"`, `"boolean"`,
+  /// `"number"`, `"string"`, `"array"` or `"object"`. Integers are numbers.
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      JsonValue::Null => "null",
+      JsonValue::Boolean(_) => "boolean",
+      JsonValue::Number(_) | JsonValue::Integer(_) | JsonValue::RawNumber(_) => "number",
+      JsonValue::String(_) => "string",
+      JsonValue::Array(_) => "array",
+      JsonValue::Object(_) => "object",
+    }
   }
 
-  fn current(&self) -> Option<char> { self.json.chars().nth(self.index) }
+  /// Returns `true` if this is `null`.
+  #[inline]
+  pub fn is_null(&self) -> bool { matches!(self, JsonValue::Null) }
 
-  fn make_string(&mut self) -> Result<JsonToken, Error> {
-    let start = self.index;
-    let mut result = String::new();
-    while let Some(c) = self.advance() {
-      match c {
-        '"' => {
-          self.advance();
-          break;
-        },
-        '\\' => match self.advance() {
-          Some('"') => result.push('"'),
-          Some('\\') => result.push('\\'),
-          Some('/') => result.push('/'),
-          Some('b') => result.push('\x08'),
-          Some('f') => result.push('\x0C'),
-          Some('n') => result.push('\n'),
-          Some('r') => result.push('\r'),
-          Some('t') => result.push('\t'),
-          Some('u') => todo!("unicode escape sequences"),
-          Some(c) => {
-            return Err(Error::json(
-              format!("Invalid escape sequence '\\{}'", c),
-              self.index - 1,
-            ))
-          },
-          None => {
-            return Err(Error::json(
-              "Unexpected end of string".to_string(),
-              self.index,
-            ))
-          },
-        },
-        _ => result.push(c),
-      }
-    }
-    Ok(JsonToken::String {
-      val: result,
-      pos: start,
+  /// Returns `true` if this is a boolean.
+  #[inline]
+  pub fn is_boolean(&self) -> bool { matches!(self, JsonValue::Boolean(_)) }
+
+  /// Returns `true` if this is a number.
+  #[inline]
+  pub fn is_number(&self) -> bool {
+    matches!(
+      self,
+      JsonValue::Number(_) | JsonValue::Integer(_) | JsonValue::RawNumber(_)
+    )
+  }
+
+  /// Returns `true` if this is a string.
+  #[inline]
+  pub fn is_string(&self) -> bool { matches!(self, JsonValue::String(_)) }
+
+  /// Returns `true` if this is an array.
+  #[inline]
+  pub fn is_array(&self) -> bool { matches!(self, JsonValue::Array(_)) }
+
+  /// Returns `true` if this is an object.
+  #[inline]
+  pub fn is_object(&self) -> bool { matches!(self, JsonValue::Object(_)) }
+
+  /// Looks up a value by an RFC 6901 JSON Pointer such as `/foo/0/bar`.
+  ///
+  /// An empty pointer refers to the whole document. Returns `None` if the
+  /// pointer is malformed, a step is missing, or a step tries to descend into
+  /// a scalar.
+  pub fn pointer(&self, ptr: &str) -> Option<&JsonValue> {
+    pointer_tokens(ptr)?.try_fold(self, |value, token| match value {
+      JsonValue::Object(obj) => obj.get(&token),
+      JsonValue::Array(arr) => pointer_index(&token).and_then(|i| arr.get(i)),
+      _ => None,
     })
   }
 
-  fn make_number(&mut self) -> Result<JsonToken, Error> {
-    let start = self.index;
-    let mut result = String::new();
-    while let Some(c) = self.current() {
-      match c {
-        '0'..='9' | '.' | '-' | '+' | 'e' | 'E' => {
-          result.push(c);
-          self.advance();
-        },
-        _ => break,
-      }
-    }
-    match result.parse::<f64>() {
-      Ok(n) => Ok(JsonToken::Number { val: n, pos: start }),
-      Err(_) => Err(Error::json(format!("Invalid number '{}'", result), start)),
-    }
+  /// Like [`JsonValue::pointer`], but returns a mutable reference, so deeply
+  /// nested values can be edited in place.
+  pub fn pointer_mut(&mut self, ptr: &str) -> Option<&mut JsonValue> {
+    pointer_walk_mut(self, pointer_tokens(ptr)?)
   }
 
-  fn make_keyword(&mut self) -> Result<JsonToken, Error> {
-    let start = self.index;
-    let mut result = String::new();
-    while let Some(c) = self.current() {
-      match c {
-        'a'..='z' => {
-          result.push(c);
-          self.advance();
-        },
-        _ => break,
+  /// Evaluates a JSONPath query and returns every matching value in document
+  /// order. The supported subset is the root `$`, children `.name` and
+  /// `['name']`, array indices `[n]` (negative ones count from the end),
+  /// wildcards `.*` and `[*]`, and recursive descent `..`, as in
+  /// `$.store.book[*].author` or `$..price`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if `path` is malformed. The Error's index is the byte
+  /// offset in `path` where it went wrong.
+  pub fn query(&self, path: &str) -> Result<Vec<&JsonValue>, Error> {
+    let mut matches = vec![self];
+    for (descendant, selector) in parse_query(path)? {
+      let mut next = vec![];
+      for value in matches {
+        let mut scope = vec![value];
+        if descendant {
+          collect_descendants(value, &mut scope);
+        }
+        for value in scope {
+          select_query(value, &selector, &mut next);
+        }
       }
+      matches = next;
     }
-    match result.as_str() {
-      "null" => Ok(JsonToken::Null { pos: start }),
-      "true" => Ok(JsonToken::Boolean {
-        val: true,
-        pos: start,
-      }),
-      "false" => Ok(JsonToken::Boolean {
-        val: false,
-        pos: start,
-      }),
-      _ => Err(Error::json(format!("Unexpected '{}'", result), start)),
+    Ok(matches)
+  }
+
+  /// Returns the number of elements of an array, the number of entries of an
+  /// object, or the length in bytes of a string. Other values have length 0.
+  pub fn len(&self) -> usize {
+    match self {
+      JsonValue::Array(arr) => arr.len(),
+      JsonValue::Object(obj) => obj.len(),
+      JsonValue::String(s) => s.len(),
+      _ => 0,
     }
   }
 
-  fn make_symbol(&mut self) -> Result<JsonToken, Error> {
-    let start = self.index;
-    let result = match self.current() {
-      Some(':') => JsonToken::Colon { pos: start },
-      Some(',') => JsonToken::Comma { pos: start },
-      Some('[') => JsonToken::LeftBracket { pos: start },
-      Some(']') => JsonToken::RightBracket { pos: start },
-      Some('{') => JsonToken::LeftBrace { pos: start },
-      Some('}') => JsonToken::RightBrace { pos: start },
-      Some(c) => return Err(Error::json(format!("Unexpected '{}'", c), start)),
-      None => JsonToken::Eof { pos: start },
+  /// Returns `true` if [`JsonValue::len`] is 0, which includes every value
+  /// that is not an array, object or string.
+  pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+  /// Iterates over the elements if this is an array. Other values yield
+  /// nothing.
+  pub fn array_iter(&self) -> impl Iterator<Item = &JsonValue> {
+    self.as_array().into_iter().flatten()
+  }
+
+  /// Iterates mutably over the elements if this is an array. Other values
+  /// yield nothing.
+  pub fn array_iter_mut(&mut self) -> impl Iterator<Item = &mut JsonValue> {
+    let arr = match self {
+      JsonValue::Array(arr) => Some(arr),
+      _ => None,
     };
-    self.advance();
-    Ok(result)
+    arr.into_iter().flatten()
   }
 
-  pub fn lex(&mut self) -> Result<Vec<JsonToken>, Error> {
-    let mut result = vec![];
-    while let Some(c) = self.current() {
-      match c {
-        ' ' | '\t' | '\n' | '\r' => {
-          while self.current().map(|c| c.is_whitespace()).unwrap_or(false) {
-            self.advance();
-          }
-        },
-        '"' => result.push(self.make_string()?),
-        '0'..='9' | '-' => result.push(self.make_number()?),
-        'f'..='t' => result.push(self.make_keyword()?),
-        ':' | ',' | '[' | ']' | '{' | '}' => result.push(self.make_symbol()?),
-        _ => return Err(Error::json(format!("Unexpected '{}'", c), self.index)),
-      }
-    }
-    Ok(result)
+  /// Iterates over the entries in order if this is an object. Other values
+  /// yield nothing.
+  pub fn entries(&self) -> impl Iterator<Item = (&String, &JsonValue)> {
+    self.as_object().into_iter().flatten()
   }
-}
 
-struct JsonParser {
-  json:   String,
-  tokens: Vec<JsonToken>,
-  index:  usize,
-}
+  /// Iterates mutably over the entries in order if this is an object. Other
+  /// values yield nothing.
+  pub fn entries_mut(&mut self) -> impl Iterator<Item = (&String, &mut JsonValue)> {
+    let obj = match self {
+      JsonValue::Object(obj) => Some(obj),
+      _ => None,
+    };
+    obj.into_iter().flatten()
+  }
 
-impl JsonParser {
-  pub fn new(json: String) -> Self {
-    Self {
-      json:   json.trim().to_string(),
-      tokens: vec![],
-      index:  0,
+  /// Inserts `value` under `key` if this is an object, returning the value
+  /// previously stored under `key`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if this is not an object.
+  pub fn insert(&mut self, key: impl Into<String>, value: JsonValue) -> Option<JsonValue> {
+    match self {
+      JsonValue::Object(obj) => obj.insert(key.into(), value),
+      _ => panic!("not an object"),
     }
   }
 
-  fn advance(&mut self) -> Option<JsonToken> {
-    self.index += 1;
-    self.tokens.get(self.index).cloned()
+  /// Returns the value under `key` if this is an object, inserting `null`
+  /// first if the key is absent.
+  ///
+  /// # Panics
+  ///
+  /// Panics if this is not an object.
+  pub fn entry(&mut self, key: &str) -> &mut JsonValue {
+    self.entry_or_insert_with(key, || JsonValue::Null)
   }
 
-  fn current(&self) -> Option<JsonToken> { self.tokens.get(self.index).cloned() }
+  /// Returns the value under `key` if this is an object, inserting the result
+  /// of `default` first if the key is absent. Handy for accumulating into an
+  /// object, as in `groups.entry_or_insert_with(k, || json!([])).push(v)`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if this is not an object.
+  pub fn entry_or_insert_with(
+    &mut self,
+    key: &str,
+    default: impl FnOnce() -> JsonValue,
+  ) -> &mut JsonValue {
+    match self {
+      JsonValue::Object(obj) => {
+        if !obj.contains_key(key) {
+          obj.insert(key.to_string(), default());
+        }
+        obj.get_mut(key).unwrap()
+      },
+      _ => panic!("not an object"),
+    }
+  }
 
-  fn parse_object(&mut self) -> Result<JsonValue, Error> {
-    let mut result = HashMap::new();
-    while let Some(token) = self.advance() {
-      match token {
-        JsonToken::RightBrace { .. } => return Ok(JsonValue::Object(result)),
-        JsonToken::String { val, pos } => {
-          match self.advance() {
-            Some(JsonToken::Colon { .. }) => (),
-            Some(
-              JsonToken::Boolean { pos, .. }
-              | JsonToken::Comma { pos, .. }
-              | JsonToken::Eof { pos, .. }
-              | JsonToken::LeftBrace { pos, .. }
-              | JsonToken::LeftBracket { pos, .. }
-              | JsonToken::Null { pos, .. }
-              | JsonToken::Number { pos, .. }
-              | JsonToken::RightBrace { pos, .. }
-              | JsonToken::RightBracket { pos, .. }
-              | JsonToken::String { pos, .. },
-            ) => return Err(Error::json("Expected ':'".to_string(), pos)),
-            None => unreachable!(),
-          }
-          if result.contains_key(&val) {
-            return Err(Error::json(format!("Duplicate key '{}'", val), pos));
-          }
-          self.advance();
-          let value = self.parse_value()?;
-          result.insert(val, value);
-          match self.advance() {
-            Some(JsonToken::Comma { .. }) => (),
-            Some(JsonToken::RightBrace { .. }) => return Ok(JsonValue::Object(result)),
-            _ => return Err(Error::json("Expected ',' or '}'".to_string(), self.index)),
-          }
-        },
-        JsonToken::Null { pos, .. }
-        | JsonToken::Number { pos, .. }
-        | JsonToken::Boolean { pos, .. }
-        | JsonToken::Colon { pos, .. }
-        | JsonToken::Comma { pos, .. }
-        | JsonToken::Eof { pos, .. }
-        | JsonToken::LeftBrace { pos, .. }
-        | JsonToken::LeftBracket { pos, .. }
-        | JsonToken::RightBracket { pos, .. } => {
-          return Err(Error::json("Expected string".to_string(), pos))
-        },
-      }
+  /// Removes `key` if this is an object and returns its value. Returns `None`
+  /// if the key is absent or this is not an object.
+  pub fn remove(&mut self, key: &str) -> Option<JsonValue> {
+    match self {
+      JsonValue::Object(obj) => obj.remove(key),
+      _ => None,
     }
+  }
 
-    Ok(JsonValue::Object(result))
+  /// Appends `value` if this is an array.
+  ///
+  /// # Panics
+  ///
+  /// Panics if this is not an array.
+  pub fn push(&mut self, value: JsonValue) {
+    match self {
+      JsonValue::Array(arr) => arr.push(value),
+      _ => panic!("not an array"),
+    }
   }
 
-  fn parse_array(&mut self) -> Result<JsonValue, Error> {
-    let mut result = Vec::new();
-    while let Some(token) = self.advance() {
-      match token {
-        JsonToken::RightBracket { .. } => return Ok(JsonValue::Array(result)),
-        JsonToken::Colon { pos } | JsonToken::Comma { pos } => {
-          return Err(Error::json("Expected a value".to_string(), pos))
-        },
-        _ => {
-          let value = self.parse_value()?;
-          result.push(value);
-          match self.advance() {
-            Some(JsonToken::Comma { .. }) => (),
-            Some(JsonToken::RightBracket { .. }) => return Ok(JsonValue::Array(result)),
-            _ => return Err(Error::json("Expected ',' or ']'".to_string(), self.index)),
-          }
-        },
+  /// Moves the value out, leaving `null` in its place. Like
+  /// [`std::mem::take`], this avoids cloning a subtree that is being moved
+  /// elsewhere.
+  pub fn take(&mut self) -> JsonValue { std::mem::take(self) }
+
+  /// Puts `value` in place of this value and returns the old one.
+  pub fn replace(&mut self, value: JsonValue) -> JsonValue { std::mem::replace(self, value) }
+
+  /// Applies `patch` to this value following RFC 7396 JSON Merge Patch.
+  ///
+  /// If `patch` is an object, each of its keys is merged recursively into this
+  /// value (which becomes an object first if it is not one), and keys whose
+  /// patch value is `null` are removed. Any other patch, including an array,
+  /// replaces this value wholesale.
+  pub fn merge(&mut self, patch: &JsonValue) {
+    let JsonValue::Object(patch) = patch else {
+      *self = patch.clone();
+      return;
+    };
+    if !self.is_object() {
+      *self = JsonValue::Object(JsonMap::new());
+    }
+    let JsonValue::Object(target) = self else {
+      unreachable!()
+    };
+    for (key, value) in patch {
+      if value.is_null() {
+        target.remove(key);
+      } else if let Some(existing) = target.get_mut(key) {
+        existing.merge(value);
+      } else {
+        let mut merged = JsonValue::Null;
+        merged.merge(value);
+        target.insert(key.clone(), merged);
       }
     }
-
-    Ok(JsonValue::Array(result))
   }
 
-  fn parse_value(&mut self) -> Result<JsonValue, Error> {
-    let val = match self.current() {
-      Some(JsonToken::String { val, .. }) => JsonValue::String(val),
-      Some(JsonToken::Number { val, .. }) => JsonValue::Number(val),
-      Some(JsonToken::Boolean { val, .. }) => JsonValue::Boolean(val),
-      Some(JsonToken::Null { .. }) => JsonValue::Null,
-      Some(JsonToken::LeftBrace { .. }) => self.parse_object()?,
-      Some(JsonToken::LeftBracket { .. }) => self.parse_array()?,
-      Some(JsonToken::Colon { pos }) => return Err(Error::json("Unexpected ':'".to_string(), pos)),
-      Some(JsonToken::Comma { pos }) => return Err(Error::json("Unexpected ','".to_string(), pos)),
-      Some(JsonToken::RightBrace { pos }) => {
-        return Err(Error::json("Unexpected '}'".to_string(), pos))
+  /// Compares two values like `==`, except that numbers only have to be
+  /// within `epsilon` of each other. Arrays are compared element by element
+  /// and objects must have the same keys, in any order.
+  pub fn approx_eq(&self, other: &JsonValue, epsilon: f64) -> bool {
+    match (self, other) {
+      (JsonValue::Array(a), JsonValue::Array(b)) => {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.approx_eq(b, epsilon))
       },
-      Some(JsonToken::RightBracket { pos }) => {
-        return Err(Error::json("Unexpected ']'".to_string(), pos))
+      (JsonValue::Object(a), JsonValue::Object(b)) => {
+        a.len() == b.len()
+          && a
+            .iter()
+            .all(|(k, a)| b.get(k).is_some_and(|b| a.approx_eq(b, epsilon)))
       },
-      Some(JsonToken::Eof { pos }) => {
-        return Err(Error::json("Unexpected end of input".to_string(), pos))
+      (a, b) => match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b || (a - b).abs() <= epsilon,
+        _ => a == b,
       },
-      None => unreachable!(),
-    };
-
-    Ok(val)
+    }
   }
 
-  pub fn parse(&mut self) -> Result<JsonValue, Error> {
-    let mut lexer = JsonLexer::new(self.json.clone());
-    self.tokens = lexer.lex()?;
-    self.parse_value()
+  /// Compares two values in the canonical order of [`PartialOrd`], but
+  /// totally: `NaN` equals `NaN` and sorts after every other number, like
+  /// positive `NaN` in [`f64::total_cmp`]. Use it to sort values that may
+  /// contain `NaN`, as in `values.sort_by(JsonValue::total_cmp)`.
+  pub fn total_cmp(&self, other: &Self) -> Ordering {
+    let numbers = |a: f64, b: f64| match (a.is_nan(), b.is_nan()) {
+      (true, true) => Some(Ordering::Equal),
+      (true, false) => Some(Ordering::Greater),
+      (false, true) => Some(Ordering::Less),
+      (false, false) => a.partial_cmp(&b),
+    };
+    self.compare_with(other, &numbers).unwrap()
   }
-}
 
-fn generate_json(val: JsonValue, pretty: i32, level: i32) -> String {
-  match val {
-    JsonValue::Null => "null".to_string(),
-    JsonValue::String(s) => format!(
-      "\"{}\"",
-      s.replace('\\', "\\\\")
-        .replace('/', "\\/")
-        .replace('\"', "\\\"")
-        .replace('\x08', "\\b")
-        .replace('\x0C', "\\f")
-        .replace('\n', "\\n")
-        .replace('\r', "\\r")
-        .replace('\t', "\\t")
-    ),
-    JsonValue::Number(n) => n.to_string(),
-    JsonValue::Boolean(b) => b.to_string(),
-    JsonValue::Array(arr) => {
-      if arr.is_empty() {
-        return "[]".to_string();
-      }
-      let mut result = "[".to_string();
-      for (i, v) in arr.iter().enumerate() {
-        if pretty == 1 {
-          result.push(' ');
-        } else if pretty == 2 {
-          result.push_str(("\n".to_string() + "  ".repeat((level + 1) as usize).as_str()).as_str());
-        }
-        result.push_str(&generate_json(v.clone(), pretty, level + 1));
-        if i < arr.len() - 1 {
-          result.push(',');
-        }
+  /// Feeds this value into `state` such that values equal under `==` hash
+  /// equally, so parsed documents can serve as cache keys. Numbers are
+  /// hashed by the bits of their `f64` value, with `-0.0` hashed as `0.0`
+  /// and every `NaN` alike, and object members in sorted key order.
+  pub fn structural_hash<H: Hasher>(&self, state: &mut H) {
+    // Nested values are visited from an explicit stack instead of recursing,
+    // with each object key right before its value.
+    let mut stack: Vec<Result<&JsonValue, &String>> = vec![Ok(self)];
+    while let Some(item) = stack.pop() {
+      let value = match item {
+        Ok(value) => value,
+        Err(key) => {
+          key.hash(state);
+          continue;
+        },
+      };
+      match value {
+        JsonValue::Null => state.write_u8(0),
+        JsonValue::Boolean(b) => {
+          state.write_u8(1);
+          b.hash(state);
+        },
+        JsonValue::Number(_) | JsonValue::Integer(_) | JsonValue::RawNumber(_) => {
+          state.write_u8(2);
+          let bits = match value.as_f64().unwrap_or(f64::NAN) {
+            n if n.is_nan() => f64::NAN.to_bits(),
+            // Also matches `-0.0`.
+            0.0 => 0,
+            n => n.to_bits(),
+          };
+          state.write_u64(bits);
+        },
+        JsonValue::String(s) => {
+          state.write_u8(3);
+          s.hash(state);
+        },
+        JsonValue::Array(arr) => {
+          state.write_u8(4);
+          state.write_usize(arr.len());
+          stack.extend(arr.iter().rev().map(Ok));
+        },
+        JsonValue::Object(obj) => {
+          state.write_u8(5);
+          state.write_usize(obj.len());
+          let mut entries: Vec<_> = obj.iter().collect();
+          entries.sort_by_key(|&(k, _)| k);
+          for (key, value) in entries.into_iter().rev() {
+            stack.push(Ok(value));
+            stack.push(Err(key));
+          }
+        },
       }
-      if pretty == 1 {
-        result.push(' ');
-      } else if pretty == 2 {
-        result.push_str(("\n".to_string() + "  ".repeat(level as usize).as_str()).as_str());
+    }
+  }
+
+  /// Compares in the canonical order, with `numbers` comparing two numbers.
+  /// Arrays and objects are walked with an explicit stack of the element
+  /// pairs still to compare instead of recursing.
+  fn compare_with(
+    &self,
+    other: &Self,
+    numbers: &impl Fn(f64, f64) -> Option<Ordering>,
+  ) -> Option<Ordering> {
+    type Entries<'a> = std::vec::IntoIter<(&'a String, &'a JsonValue)>;
+    enum Pairs<'a> {
+      Array(
+        std::slice::Iter<'a, JsonValue>,
+        std::slice::Iter<'a, JsonValue>,
+      ),
+      Object(Entries<'a>, Entries<'a>),
+    }
+    fn sorted(obj: &JsonMap) -> Entries<'_> {
+      let mut entries: Vec<_> = obj.iter().collect();
+      entries.sort_by_key(|&(k, _)| k);
+      entries.into_iter()
+    }
+    let mut stack = vec![];
+    let (mut a, mut b) = (self, other);
+    loop {
+      match (a, b) {
+        (JsonValue::Array(a), JsonValue::Array(b)) => {
+          stack.push(Pairs::Array(a.iter(), b.iter()));
+        },
+        (JsonValue::Object(a), JsonValue::Object(b)) => {
+          stack.push(Pairs::Object(sorted(a), sorted(b)));
+        },
+        _ => match a.compare_scalar(b, numbers)? {
+          Ordering::Equal => (),
+          ordering => return Some(ordering),
+        },
       }
-      result.push(']');
-      result
-    },
-    JsonValue::Object(obj) => {
-      if obj.is_empty() {
-        return "{}".to_string();
-      }
-      let mut result = "{".to_string();
-      for (i, (k, v)) in obj.iter().enumerate() {
-        if pretty == 1 {
-          result.push(' ');
-        } else if pretty == 2 {
-          result.push_str(("\n".to_string() + "  ".repeat((level + 1) as usize).as_str()).as_str());
-        }
-        result.push_str(&format!(
-          "\"{}\":{}{}",
-          k,
-          if [1, 2].contains(&pretty) { " " } else { "" },
-          generate_json(v.clone(), pretty, level + 1)
-        ));
-        if i < obj.len() - 1 {
-          result.push(',');
+      // Move on to the next pair, closing the containers that are done.
+      loop {
+        let next = match stack.last_mut() {
+          None => return Some(Ordering::Equal),
+          Some(Pairs::Array(a, b)) => match (a.next(), b.next()) {
+            (Some(a), Some(b)) => Some((a, b)),
+            (a, b) => match a.is_some().cmp(&b.is_some()) {
+              Ordering::Equal => None,
+              ordering => return Some(ordering),
+            },
+          },
+          Some(Pairs::Object(a, b)) => match (a.next(), b.next()) {
+            (Some((ka, va)), Some((kb, vb))) => match ka.cmp(kb) {
+              Ordering::Equal => Some((va, vb)),
+              ordering => return Some(ordering),
+            },
+            (a, b) => match a.is_some().cmp(&b.is_some()) {
+              Ordering::Equal => None,
+              ordering => return Some(ordering),
+            },
+          },
+        };
+        match next {
+          Some(pair) => {
+            (a, b) = pair;
+            break;
+          },
+          None => {
+            stack.pop();
+          },
         }
       }
-      if pretty == 1 {
-        result.push(' ');
-      } else if pretty == 2 {
-        result.push_str(("\n".to_string() + "  ".repeat(level as usize).as_str()).as_str());
-      }
-      result.push('}');
-      result
-    },
+    }
   }
-}
 
-/// Struct with methods for parsing and stringifying JSON similar to the
-/// JavaScript JSON object.
-pub struct JSON {}
+  /// Compares two values of which at least one is not an array or object.
+  fn compare_scalar(
+    &self,
+    other: &Self,
+    numbers: &impl Fn(f64, f64) -> Option<Ordering>,
+  ) -> Option<Ordering> {
+    let rank = |value: &JsonValue| match value {
+      JsonValue::Null => 0,
+      JsonValue::Boolean(_) => 1,
+      JsonValue::Number(_) | JsonValue::Integer(_) | JsonValue::RawNumber(_) => 2,
+      JsonValue::String(_) => 3,
+      JsonValue::Array(_) => 4,
+      JsonValue::Object(_) => 5,
+    };
+    match (self, other) {
+      (JsonValue::Null, JsonValue::Null) => Some(Ordering::Equal),
+      (JsonValue::Boolean(a), JsonValue::Boolean(b)) => Some(a.cmp(b)),
+      (JsonValue::Integer(a), JsonValue::Integer(b)) => Some(a.cmp(b)),
+      (JsonValue::Integer(a), JsonValue::Number(b)) => compare_integer_float(*a, *b, numbers),
+      (JsonValue::Number(a), JsonValue::Integer(b)) => {
+        compare_integer_float(*b, *a, numbers).map(Ordering::reverse)
+      },
+      (JsonValue::Number(a), JsonValue::Number(b)) => numbers(*a, *b),
+      (JsonValue::RawNumber(a), JsonValue::RawNumber(b)) => {
+        raw_number_value(a).compare_with(&raw_number_value(b), numbers)
+      },
+      (JsonValue::RawNumber(a), b) if b.is_number() => raw_number_value(a).compare_with(b, numbers),
+      (a, JsonValue::RawNumber(b)) if a.is_number() => {
+        a.compare_with(&raw_number_value(b), numbers)
+      },
+      (JsonValue::String(a), JsonValue::String(b)) => Some(a.cmp(b)),
+      _ => Some(rank(self).cmp(&rank(other))),
+    }
+  }
 
-impl JSON {
-  /// Parses a JSON string and returns a JsonValue struct.
-  ///
-  /// # Arguments
-  ///
-  /// - `json` - The JSON string to parse.
+  /// Flattens this value into its scalar leaves, keyed by their dotted paths
+  /// such as `user.address.city` or `items.0`. Empty arrays and objects have
+  /// no leaves and are left out. A scalar at the top is keyed by the empty
+  /// path `""`.
+  pub fn flatten(&self) -> HashMap<String, JsonValue> { self.flatten_with(".") }
+
+  /// Flattens this value like [`JsonValue::flatten`], but joins path segments
+  /// with `separator` instead of `.`.
+  pub fn flatten_with(&self, separator: &str) -> HashMap<String, JsonValue> {
+    let mut out = HashMap::new();
+    flatten_into(self, &mut String::new(), separator, &mut out);
+    out
+  }
+
+  /// Rebuilds a nested value from dotted paths and their values, the inverse
+  /// of [`JsonValue::flatten`]. Path segments that are array indices (`0`,
+  /// `1`, ...) build arrays, other segments objects. Object keys end up in
+  /// sorted order. An empty map gives an empty object.
   ///
   /// # Errors
   ///
-  /// Returns an Error if the JSON string is invalid.
-  pub fn parse(json: String) -> Result<JsonValue, Error> {
-    let mut parser = JsonParser::new(json);
-    parser.parse()
+  /// Returns an Error if two paths conflict, e.g. `a` and `a.b`, or `a.0`
+  /// and `a.b`, or if an array index is skipped, e.g. `a.0` and `a.2`
+  /// without `a.1`. The message names the offending path; as paths have no
+  /// position in any source text, the error's `index` is always 0.
+  pub fn unflatten(map: &HashMap<String, JsonValue>) -> Result<JsonValue, Error> {
+    JsonValue::unflatten_with(map, ".")
   }
 
-  /// Stringifies a JsonValue struct and returns a JSON string.
-  ///
-  /// # Arguments
-  ///
-  /// - `value` - The JsonValue struct to stringify.
-  /// - `pretty` - The level of pretty formatting to use. 0 for no whitespace, 1
-  ///   for spaces, 2 for newlines.
+  /// Rebuilds a nested value like [`JsonValue::unflatten`], but splits paths
+  /// at `separator` instead of `.`.
   ///
   /// # Errors
   ///
-  /// Never returns an Error.
-  pub fn stringify(value: JsonValue, pretty: i32) -> String { generate_json(value, pretty, 0) }
+  /// Returns an Error if two paths conflict or an array index is skipped.
+  pub fn unflatten_with(
+    map: &HashMap<String, JsonValue>,
+    separator: &str,
+  ) -> Result<JsonValue, Error> {
+    if let Some(value) = map.get("") {
+      return match map.len() {
+        1 => Ok(value.clone()),
+        _ => Err(unflatten_conflict("")),
+      };
+    }
+    // In this order a leaf comes before the paths below it and array
+    // elements come in index order, so every new index is the next one.
+    let mut paths: Vec<_> = map
+      .iter()
+      .map(|(path, value)| (path.split(separator).collect::<Vec<_>>(), path, value))
+      .collect();
+    paths.sort_by(|(a, ..), (b, ..)| {
+      let segments = |a: &&str, b: &&str| match (pointer_index(a), pointer_index(b)) {
+        (Some(a), Some(b)) => Some(a.cmp(&b)),
+        _ => Some(a.cmp(b)),
+      };
+      compare_lexicographic(a, b, segments).unwrap()
+    });
+    let mut root = None;
+    for (segments, path, value) in paths {
+      let root = root.get_or_insert_with(|| unflatten_container(segments[0]));
+      unflatten_insert(root, &segments, path, value.clone())?;
+    }
+    Ok(root.unwrap_or_else(|| JsonValue::Object(JsonMap::new())))
+  }
+
+  /// Returns a deep copy in which the keys of every object, however deeply
+  /// nested, are in sorted order. Useful for canonical output and stable
+  /// diffs.
+  pub fn sorted(&self) -> JsonValue {
+    match self {
+      JsonValue::Array(arr) => JsonValue::Array(arr.iter().map(JsonValue::sorted).collect()),
+      JsonValue::Object(obj) => {
+        let mut entries: Vec<_> = obj.iter().map(|(k, v)| (k.clone(), v.sorted())).collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        JsonValue::Object(entries.into_iter().collect())
+      },
+      value => value.clone(),
+    }
+  }
+
+  /// Applies a list of RFC 6902 JSON Patch operations to this value.
+  ///
+  /// `ops` must be an array of operation objects such as
+  /// `{"op": "add", "path": "/a/0", "value": 1}`. The `add`, `remove`,
+  /// `replace`, `move`, `copy` and `test` operations are supported, with
+  /// locations given as RFC 6901 JSON Pointers. The patch is atomic: if any
+  /// operation fails, this value is left unchanged.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if `ops` is malformed, a path does not exist, or a
+  /// `test` operation fails. The Error's index is the position of the failing
+  /// operation in `ops`.
+  pub fn apply_patch(&mut self, ops: &JsonValue) -> Result<(), Error> {
+    let ops = ops
+      .as_array()
+      .ok_or_else(|| Error::json("Patch must be an array of operations".to_string(), 0))?;
+    let mut result = self.clone();
+    for (i, op) in ops.iter().enumerate() {
+      apply_patch_operation(&mut result, op).map_err(|message| Error::json(message, i))?;
+    }
+    *self = result;
+    Ok(())
+  }
+
+  /// Describes how `other` differs from this value. The result is an object
+  /// keyed by the JSON Pointer of every place that differs, with the value
+  /// `{"old": ..., "new": ...}` for a change, `{"new": ...}` for an added key
+  /// or element and `{"old": ...}` for a removed one. Objects are compared key
+  /// by key and arrays index by index, recursively; values of different types
+  /// count as a change of the whole value. Equal values give an empty object.
+  pub fn diff(&self, other: &JsonValue) -> JsonValue {
+    let mut out = JsonMap::new();
+    diff_into(Some(self), Some(other), &mut String::new(), &mut out);
+    JsonValue::Object(out)
+  }
+
+  /// Returns the string slice if this is a string, or `None` otherwise.
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      JsonValue::String(s) => Some(s),
+      _ => None,
+    }
+  }
+
+  /// Returns the number if this is a number, or `None` otherwise. Integers
+  /// and raw numbers are converted and may lose precision beyond 2^53.
+  pub fn as_f64(&self) -> Option<f64> {
+    match self {
+      JsonValue::Number(n) => Some(*n),
+      JsonValue::Integer(i) => Some(*i as f64),
+      JsonValue::RawNumber(raw) => raw.parse().ok(),
+      _ => None,
+    }
+  }
+
+  /// Returns the integer if this is an integer, or a raw number with an
+  /// integer literal that fits, or `None` otherwise.
+  pub fn as_i64(&self) -> Option<i64> {
+    match self {
+      JsonValue::Integer(i) => Some(*i),
+      JsonValue::RawNumber(raw) => raw_number_value(raw).as_i64(),
+      _ => None,
+    }
+  }
+
+  /// Returns the boolean if this is a boolean, or `None` otherwise.
+  pub fn as_bool(&self) -> Option<bool> {
+    match self {
+      JsonValue::Boolean(b) => Some(*b),
+      _ => None,
+    }
+  }
+
+  /// Returns the elements if this is an array, or `None` otherwise.
+  pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+    match self {
+      JsonValue::Array(arr) => Some(arr),
+      _ => None,
+    }
+  }
+
+  /// Returns the entries if this is an object, or `None` otherwise.
+  pub fn as_object(&self) -> Option<&JsonMap> {
+    match self {
+      JsonValue::Object(obj) => Some(obj),
+      _ => None,
+    }
+  }
+}
+
+/// Splits a JSON Pointer into its unescaped reference tokens, or returns
+/// `None` if it is neither empty nor starts with `/`.
+fn pointer_tokens(ptr: &str) -> Option<impl Iterator<Item = String> + '_> {
+  if !ptr.is_empty() && !ptr.starts_with('/') {
+    return None;
+  }
+  Some(
+    ptr
+      .split('/')
+      .skip(1)
+      .map(|token| token.replace("~1", "/").replace("~0", "~")),
+  )
+}
+
+/// Parses a JSON Pointer token as an array index. Leading zeros and signs are
+/// not allowed.
+fn pointer_index(token: &str) -> Option<usize> {
+  if token.is_empty()
+    || !token.bytes().all(|b| b.is_ascii_digit())
+    || (token.len() > 1 && token.starts_with('0'))
+  {
+    return None;
+  }
+  token.parse().ok()
+}
+
+/// One step of a [`JsonValue::query`] path.
+enum QuerySelector {
+  Name(String),
+  Index(i64),
+  Wildcard,
+}
+
+/// Splits a JSONPath into steps, each flagged with whether it applies to all
+/// descendants (`..`) rather than just children.
+fn parse_query(path: &str) -> Result<Vec<(bool, QuerySelector)>, Error> {
+  let error = |message: &str, index| Err(Error::json(format!("Invalid path: {}", message), index));
+  let bytes = path.as_bytes();
+  if bytes.first() != Some(&b'$') {
+    return error("expected '$'", 0);
+  }
+  let mut steps = vec![];
+  let mut i = 1;
+  while i < bytes.len() {
+    let mut descendant = false;
+    match bytes[i] {
+      b'.' if bytes.get(i + 1) == Some(&b'.') => {
+        descendant = true;
+        i += 2;
+      },
+      b'.' => i += 1,
+      b'[' => (),
+      _ => return error("expected '.' or '['", i),
+    }
+    if bytes.get(i) == Some(&b'[') {
+      let end = match path[i..].find(']') {
+        Some(end) => i + end,
+        None => return error("unterminated '['", i),
+      };
+      let inner = path[i + 1..end].trim();
+      let selector = if inner == "*" {
+        QuerySelector::Wildcard
+      } else if let Ok(n) = inner.parse::<i64>() {
+        QuerySelector::Index(n)
+      } else if inner.len() >= 2
+        && (inner.starts_with('\'') && inner.ends_with('\'')
+          || inner.starts_with('"') && inner.ends_with('"'))
+      {
+        QuerySelector::Name(inner[1..inner.len() - 1].to_string())
+      } else {
+        return error("expected a quoted name, an index or '*'", i + 1);
+      };
+      steps.push((descendant, selector));
+      i = end + 1;
+    } else {
+      let len = path[i..].find(['.', '[']).unwrap_or(path.len() - i);
+      let selector = match &path[i..i + len] {
+        "" => return error("expected a name", i),
+        "*" => QuerySelector::Wildcard,
+        name => QuerySelector::Name(name.to_string()),
+      };
+      steps.push((descendant, selector));
+      i += len;
+    }
+  }
+  Ok(steps)
+}
+
+/// Appends every value nested inside `value` to `out`, in document order.
+fn collect_descendants<'v>(value: &'v JsonValue, out: &mut Vec<&'v JsonValue>) {
+  let children: Box<dyn Iterator<Item = &JsonValue>> = match value {
+    JsonValue::Array(arr) => Box::new(arr.iter()),
+    JsonValue::Object(obj) => Box::new(obj.values()),
+    _ => return,
+  };
+  for child in children {
+    out.push(child);
+    collect_descendants(child, out);
+  }
+}
+
+fn select_query<'v>(value: &'v JsonValue, selector: &QuerySelector, out: &mut Vec<&'v JsonValue>) {
+  match (selector, value) {
+    (QuerySelector::Name(name), JsonValue::Object(obj)) => out.extend(obj.get(name)),
+    (QuerySelector::Index(i), JsonValue::Array(arr)) => {
+      let i = match *i < 0 {
+        true => arr.len() as i64 + i,
+        false => *i,
+      };
+      out.extend(usize::try_from(i).ok().and_then(|i| arr.get(i)));
+    },
+    (QuerySelector::Wildcard, JsonValue::Array(arr)) => out.extend(arr),
+    (QuerySelector::Wildcard, JsonValue::Object(obj)) => out.extend(obj.values()),
+    _ => (),
+  }
+}
+
+/// Walks `tokens` down from `value`, like [`JsonValue::pointer_mut`].
+fn pointer_walk_mut(
+  value: &mut JsonValue,
+  tokens: impl IntoIterator<Item = String>,
+) -> Option<&mut JsonValue> {
+  tokens
+    .into_iter()
+    .try_fold(value, |value, token| match value {
+      JsonValue::Object(obj) => obj.get_mut(&token),
+      JsonValue::Array(arr) => pointer_index(&token).and_then(|i| arr.get_mut(i)),
+      _ => None,
+    })
+}
+
+fn apply_patch_operation(doc: &mut JsonValue, op: &JsonValue) -> Result<(), String> {
+  let field = |name: &str| op.get(name).ok_or_else(|| format!("Missing '{}'", name));
+  let pointer = |name: &str| {
+    field(name)?
+      .as_str()
+      .ok_or_else(|| format!("'{}' must be a string", name))
+  };
+  let path = pointer("path")?;
+  match op.get("op").and_then(JsonValue::as_str) {
+    Some("add") => patch_add(doc, path, field("value")?.clone()),
+    Some("remove") => patch_remove(doc, path).map(drop),
+    Some("replace") => {
+      let value = field("value")?.clone();
+      *patch_target(doc, path)? = value;
+      Ok(())
+    },
+    Some("move") => {
+      let from = pointer("from")?;
+      if from == path {
+        return patch_target(doc, path).map(drop);
+      }
+      if path.starts_with(from) && path[from.len()..].starts_with('/') {
+        return Err(format!("Cannot move '{}' into itself", from));
+      }
+      let value = patch_remove(doc, from)?;
+      patch_add(doc, path, value)
+    },
+    Some("copy") => {
+      let from = pointer("from")?;
+      let value = patch_target(doc, from)?.clone();
+      patch_add(doc, path, value)
+    },
+    Some("test") => match patch_target(doc, path)? == field("value")? {
+      true => Ok(()),
+      false => Err(format!("Test failed at '{}'", path)),
+    },
+    Some(other) => Err(format!("Unknown operation '{}'", other)),
+    None => Err("Missing 'op'".to_string()),
+  }
+}
+
+fn patch_target<'v>(doc: &'v mut JsonValue, path: &str) -> Result<&'v mut JsonValue, String> {
+  doc
+    .pointer_mut(path)
+    .ok_or_else(|| format!("Path '{}' does not exist", path))
+}
+
+/// Resolves everything but the last token of `path`, returning the container
+/// and the last token. `path` must not be the root.
+fn patch_parent<'v>(
+  doc: &'v mut JsonValue,
+  path: &str,
+) -> Result<(&'v mut JsonValue, String), String> {
+  let mut tokens: Vec<String> = pointer_tokens(path)
+    .ok_or_else(|| format!("Invalid pointer '{}'", path))?
+    .collect();
+  let last = tokens.pop().unwrap();
+  let parent =
+    pointer_walk_mut(doc, tokens).ok_or_else(|| format!("Path '{}' does not exist", path))?;
+  Ok((parent, last))
+}
+
+fn patch_add(doc: &mut JsonValue, path: &str, value: JsonValue) -> Result<(), String> {
+  if path.is_empty() {
+    *doc = value;
+    return Ok(());
+  }
+  match patch_parent(doc, path)? {
+    (JsonValue::Object(obj), key) => {
+      obj.insert(key, value);
+      Ok(())
+    },
+    (JsonValue::Array(arr), index) if index == "-" => {
+      arr.push(value);
+      Ok(())
+    },
+    (JsonValue::Array(arr), index) => match pointer_index(&index) {
+      Some(i) if i <= arr.len() => {
+        arr.insert(i, value);
+        Ok(())
+      },
+      _ => Err(format!("Invalid array index in '{}'", path)),
+    },
+    _ => Err(format!("Path '{}' does not exist", path)),
+  }
+}
+
+fn patch_remove(doc: &mut JsonValue, path: &str) -> Result<JsonValue, String> {
+  if path.is_empty() {
+    return Err("Cannot remove the whole document".to_string());
+  }
+  let removed = match patch_parent(doc, path)? {
+    (JsonValue::Object(obj), key) => obj.remove(&key),
+    (JsonValue::Array(arr), index) => match pointer_index(&index) {
+      Some(i) if i < arr.len() => Some(arr.remove(i)),
+      _ => None,
+    },
+    _ => None,
+  };
+  removed.ok_or_else(|| format!("Path '{}' does not exist", path))
+}
+
+impl Display for JsonValue {
+  fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+    write_json(self, &StringifyOptions::default(), f)
+  }
+}
+
+/// Drops nested arrays and objects one level at a time instead of recursing,
+/// so a value nested arbitrarily deep does not overflow the stack.
+impl Drop for JsonValue {
+  fn drop(&mut self) {
+    let mut stack = vec![];
+    take_children(self, &mut stack);
+    while let Some(mut value) = stack.pop() {
+      take_children(&mut value, &mut stack);
+    }
+  }
+}
+
+/// Rebuilds nested arrays and objects from an explicit stack instead of
+/// recursing, so a value nested arbitrarily deep clones without overflowing
+/// the stack.
+impl Clone for JsonValue {
+  fn clone(&self) -> Self {
+    enum Frame<'a> {
+      Array(std::slice::Iter<'a, JsonValue>, Vec<JsonValue>),
+      Object(JsonMapIter<'a>, JsonMap, Option<&'a String>),
+    }
+    let mut stack = vec![];
+    let mut value = self;
+    loop {
+      let mut cloned = match value {
+        JsonValue::Null => Some(JsonValue::Null),
+        JsonValue::String(s) => Some(JsonValue::String(s.clone())),
+        JsonValue::Number(n) => Some(JsonValue::Number(*n)),
+        JsonValue::Integer(i) => Some(JsonValue::Integer(*i)),
+        JsonValue::RawNumber(raw) => Some(JsonValue::RawNumber(raw.clone())),
+        JsonValue::Boolean(b) => Some(JsonValue::Boolean(*b)),
+        JsonValue::Array(arr) => {
+          stack.push(Frame::Array(arr.iter(), Vec::with_capacity(arr.len())));
+          None
+        },
+        JsonValue::Object(obj) => {
+          stack.push(Frame::Object(obj.iter(), JsonMap::new(), None));
+          None
+        },
+      };
+      // Store the clone in the innermost container and move on to the next
+      // value to clone, closing the containers that are done.
+      loop {
+        let next = match stack.last_mut() {
+          None => return cloned.unwrap(),
+          Some(Frame::Array(source, arr)) => {
+            arr.extend(cloned.take());
+            source.next()
+          },
+          Some(Frame::Object(source, obj, key)) => {
+            if let Some(cloned) = cloned.take() {
+              obj.insert(key.take().unwrap().clone(), cloned);
+            }
+            source.next().map(|(next_key, next)| {
+              *key = Some(next_key);
+              next
+            })
+          },
+        };
+        match next {
+          Some(next) => {
+            value = next;
+            break;
+          },
+          None => {
+            cloned = Some(match stack.pop().unwrap() {
+              Frame::Array(_, arr) => JsonValue::Array(arr),
+              Frame::Object(_, obj, _) => JsonValue::Object(obj),
+            });
+          },
+        }
+      }
+    }
+  }
+}
+
+/// Compares nested arrays and objects from an explicit stack instead of
+/// recursing; see [`values_eq`].
+impl PartialEq for JsonValue {
+  fn eq(&self, other: &Self) -> bool { values_eq(vec![(self, other)]) }
+}
+
+/// Whether the values of every pair in `pairs` are equal. Arrays and objects
+/// push the pairs of their elements or members instead of recursing, so
+/// values nested arbitrarily deep compare without overflowing the stack.
+fn values_eq<'a>(mut pairs: Vec<(&'a JsonValue, &'a JsonValue)>) -> bool {
+  while let Some((a, b)) = pairs.pop() {
+    let equal = match (a, b) {
+      (JsonValue::Array(a), JsonValue::Array(b)) => {
+        pairs.extend(a.iter().zip(b));
+        a.len() == b.len()
+      },
+      (JsonValue::Object(a), JsonValue::Object(b)) => {
+        a.len() == b.len()
+          && a.iter().all(|(key, a)| match b.get(key) {
+            Some(b) => {
+              pairs.push((a, b));
+              true
+            },
+            None => false,
+          })
+      },
+      (a, b) => a.scalar_eq(b),
+    };
+    if !equal {
+      return false;
+    }
+  }
+  true
+}
+
+impl JsonValue {
+  /// Compares two values of which at least one is not an array or object.
+  fn scalar_eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (JsonValue::Null, JsonValue::Null) => true,
+      (JsonValue::String(a), JsonValue::String(b)) => a == b,
+      (JsonValue::Number(a), JsonValue::Number(b)) => a == b,
+      (JsonValue::Integer(a), JsonValue::Integer(b)) => a == b,
+      (JsonValue::Integer(a), JsonValue::Number(b))
+      | (JsonValue::Number(b), JsonValue::Integer(a)) => {
+        compare_integer_float(*a, *b, &|a, b| a.partial_cmp(&b)) == Some(Ordering::Equal)
+      },
+      (JsonValue::RawNumber(a), JsonValue::RawNumber(b)) => {
+        raw_number_value(a) == raw_number_value(b)
+      },
+      (JsonValue::RawNumber(a), b) | (b, JsonValue::RawNumber(a)) => raw_number_value(a) == *b,
+      (JsonValue::Boolean(a), JsonValue::Boolean(b)) => a == b,
+      _ => false,
+    }
+  }
+}
+
+/// Moves the elements or member values of `value` onto `stack`, leaving it
+/// empty.
+fn take_children(value: &mut JsonValue, stack: &mut Vec<JsonValue>) {
+  match value {
+    JsonValue::Array(arr) => stack.append(arr),
+    JsonValue::Object(obj) => {
+      obj.indices.clear();
+      stack.extend(obj.entries.drain(..).map(|(_, value)| value));
+    },
+    _ => (),
+  }
+}
+
+/// Adds the scalar leaves of `value`, found at `path`, to `out`.
+fn flatten_into(
+  value: &JsonValue,
+  path: &mut String,
+  separator: &str,
+  out: &mut HashMap<String, JsonValue>,
+) {
+  let mut descend = |key: &str, value: &JsonValue| {
+    let len = path.len();
+    if len > 0 {
+      path.push_str(separator);
+    }
+    path.push_str(key);
+    flatten_into(value, path, separator, out);
+    path.truncate(len);
+  };
+  match value {
+    JsonValue::Array(arr) => arr
+      .iter()
+      .enumerate()
+      .for_each(|(i, v)| descend(&i.to_string(), v)),
+    JsonValue::Object(obj) => obj.iter().for_each(|(k, v)| descend(k, v)),
+    value => {
+      out.insert(path.clone(), value.clone());
+    },
+  }
+}
+
+/// Records the differences between `old` and `new` at `path` into `out`,
+/// where `None` stands for a missing key or element.
+fn diff_into(
+  old: Option<&JsonValue>,
+  new: Option<&JsonValue>,
+  path: &mut String,
+  out: &mut JsonMap,
+) {
+  let mut descend = |key: &str, old: Option<&JsonValue>, new: Option<&JsonValue>| {
+    let len = path.len();
+    path.push('/');
+    path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+    diff_into(old, new, path, out);
+    path.truncate(len);
+  };
+  match (old, new) {
+    (Some(a), Some(b)) if a == b => (),
+    (Some(JsonValue::Array(a)), Some(JsonValue::Array(b))) => {
+      for i in 0..a.len().max(b.len()) {
+        descend(&i.to_string(), a.get(i), b.get(i));
+      }
+    },
+    (Some(JsonValue::Object(a)), Some(JsonValue::Object(b))) => {
+      for (key, value) in a {
+        descend(key, Some(value), b.get(key));
+      }
+      for (key, value) in b.iter().filter(|(key, _)| !a.contains_key(key)) {
+        descend(key, None, Some(value));
+      }
+    },
+    (old, new) => {
+      let mut change = JsonMap::new();
+      if let Some(old) = old {
+        change.insert("old".to_string(), old.clone());
+      }
+      if let Some(new) = new {
+        change.insert("new".to_string(), new.clone());
+      }
+      out.insert(path.clone(), JsonValue::Object(change));
+    },
+  }
+}
+
+/// An empty array if `segment` is an array index, or an empty object.
+fn unflatten_container(segment: &str) -> JsonValue {
+  match pointer_index(segment) {
+    Some(_) => JsonValue::Array(vec![]),
+    None => JsonValue::Object(JsonMap::new()),
+  }
+}
+
+/// Builds the error for a path that conflicts with another.
+fn unflatten_conflict(path: &str) -> Error {
+  Error::json(format!("Path '{}' conflicts with another path", path), 0)
+}
+
+/// Inserts `value` at `segments` below `node`, creating containers on the way.
+/// `path` is the unsplit path, used in errors.
+fn unflatten_insert(
+  mut node: &mut JsonValue,
+  segments: &[&str],
+  path: &str,
+  value: JsonValue,
+) -> Result<(), Error> {
+  let mut value = Some(value);
+  for (i, segment) in segments.iter().enumerate() {
+    let last = i + 1 == segments.len();
+    let mut fresh = || match last {
+      true => value.take().unwrap(),
+      false => unflatten_container(segments[i + 1]),
+    };
+    node = match (node, pointer_index(segment)) {
+      (JsonValue::Array(arr), Some(index)) => {
+        if index > arr.len() {
+          let message = format!("Path '{}' skips an array index", path);
+          return Err(Error::json(message, 0));
+        } else if index == arr.len() {
+          arr.push(fresh());
+        } else if last {
+          return Err(unflatten_conflict(path));
+        }
+        &mut arr[index]
+      },
+      (JsonValue::Object(obj), None) => {
+        if !obj.contains_key(segment) {
+          obj.insert(segment.to_string(), fresh());
+        } else if last {
+          return Err(unflatten_conflict(path));
+        }
+        obj.get_mut(segment).unwrap()
+      },
+      _ => return Err(unflatten_conflict(path)),
+    };
+  }
+  Ok(())
+}
+
+/// Compares `a` with `b` exactly, without rounding `a` to an `f64`. `numbers`
+/// only decides how `NaN` compares.
+fn compare_integer_float(
+  a: i64,
+  b: f64,
+  numbers: &impl Fn(f64, f64) -> Option<Ordering>,
+) -> Option<Ordering> {
+  // 2^63, the first `f64` above `i64::MAX`; `i64::MIN` is exactly -2^63.
+  const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+  if b.is_nan() {
+    return numbers(a as f64, b);
+  }
+  if b >= LIMIT {
+    return Some(Ordering::Less);
+  }
+  if b < -LIMIT {
+    return Some(Ordering::Greater);
+  }
+  match a.cmp(&(b.trunc() as i64)) {
+    Ordering::Equal => 0.0.partial_cmp(&b.fract()),
+    ordering => Some(ordering),
+  }
+}
+
+/// Converts a raw number literal into an [`JsonValue::Integer`] if it is an
+/// integer that fits, or a [`JsonValue::Number`] otherwise.
+fn raw_number_value(raw: &str) -> JsonValue {
+  match raw.parse() {
+    Ok(i) if !raw.contains(['.', 'e', 'E']) => JsonValue::Integer(i),
+    _ => JsonValue::Number(raw.parse().unwrap_or(f64::NAN)),
+  }
+}
+
+/// Compares `a` and `b` item by item with `compare`, the shorter one first
+/// if one is a prefix of the other.
+fn compare_lexicographic<T>(
+  a: &[T],
+  b: &[T],
+  compare: impl Fn(&T, &T) -> Option<Ordering>,
+) -> Option<Ordering> {
+  for (a, b) in a.iter().zip(b) {
+    match compare(a, b)? {
+      Ordering::Equal => (),
+      ordering => return Some(ordering),
+    }
+  }
+  Some(a.len().cmp(&b.len()))
+}
+
+/// Orders values first by type, `null` < booleans < numbers < strings <
+/// arrays < objects, then within a type: `false` < `true`, numbers by value
+/// (integers and floats together), strings by their bytes, arrays element
+/// by element with a shorter prefix first, and objects like arrays of their
+/// `(key, value)` pairs sorted by key. This agrees with [`PartialEq`], so a
+/// comparison involving `NaN`, anywhere inside the values, returns `None`;
+/// see [`JsonValue::total_cmp`] for a total order.
+impl PartialOrd for JsonValue {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    self.compare_with(other, &|a: f64, b: f64| a.partial_cmp(&b))
+  }
+}
+
+impl From<bool> for JsonValue {
+  fn from(b: bool) -> Self { JsonValue::Boolean(b) }
+}
+
+impl From<f64> for JsonValue {
+  fn from(n: f64) -> Self { JsonValue::Number(n) }
+}
+
+impl From<i64> for JsonValue {
+  fn from(n: i64) -> Self { JsonValue::Integer(n) }
+}
+
+impl From<&str> for JsonValue {
+  fn from(s: &str) -> Self { JsonValue::String(s.to_string()) }
+}
+
+impl From<String> for JsonValue {
+  fn from(s: String) -> Self { JsonValue::String(s) }
+}
+
+impl From<Vec<JsonValue>> for JsonValue {
+  fn from(arr: Vec<JsonValue>) -> Self { JsonValue::Array(arr) }
+}
+
+impl From<HashMap<String, JsonValue>> for JsonValue {
+  fn from(obj: HashMap<String, JsonValue>) -> Self { JsonValue::Object(obj.into()) }
+}
+
+impl From<JsonMap> for JsonValue {
+  fn from(obj: JsonMap) -> Self { JsonValue::Object(obj) }
+}
+
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+  fn from(opt: Option<T>) -> Self {
+    match opt {
+      Some(v) => v.into(),
+      None => JsonValue::Null,
+    }
+  }
+}
+
+/// Collects values into a [`JsonValue::Array`].
+impl FromIterator<JsonValue> for JsonValue {
+  fn from_iter<I: IntoIterator<Item = JsonValue>>(iter: I) -> Self {
+    JsonValue::Array(iter.into_iter().collect())
+  }
+}
+
+/// Collects key/value pairs into a [`JsonValue::Object`]. A repeated key
+/// keeps its first position and its last value.
+impl FromIterator<(String, JsonValue)> for JsonValue {
+  fn from_iter<I: IntoIterator<Item = (String, JsonValue)>>(iter: I) -> Self {
+    JsonValue::Object(iter.into_iter().collect())
+  }
+}
+
+/// Iterates over the elements of an array, so `for item in value` works.
+/// Every other value, including an object, yields nothing; use
+/// [`JsonValue::entries`] for object members.
+impl IntoIterator for JsonValue {
+  type IntoIter = std::vec::IntoIter<JsonValue>;
+  type Item = JsonValue;
+
+  fn into_iter(mut self) -> Self::IntoIter {
+    match &mut self {
+      JsonValue::Array(arr) => std::mem::take(arr).into_iter(),
+      _ => Vec::new().into_iter(),
+    }
+  }
+}
+
+/// Iterates over the elements of an array by reference, like
+/// [`JsonValue::array_iter`]. Every other value yields nothing.
+impl<'a> IntoIterator for &'a JsonValue {
+  type IntoIter = std::slice::Iter<'a, JsonValue>;
+  type Item = &'a JsonValue;
+
+  fn into_iter(self) -> Self::IntoIter {
+    match self {
+      JsonValue::Array(arr) => arr.iter(),
+      _ => [].iter(),
+    }
+  }
+}
+
+/// Iterates mutably over the elements of an array, like
+/// [`JsonValue::array_iter_mut`]. Every other value yields nothing.
+impl<'a> IntoIterator for &'a mut JsonValue {
+  type IntoIter = std::slice::IterMut<'a, JsonValue>;
+  type Item = &'a mut JsonValue;
+
+  fn into_iter(self) -> Self::IntoIter {
+    match self {
+      JsonValue::Array(arr) => arr.iter_mut(),
+      _ => [].iter_mut(),
+    }
+  }
+}
+
+/// Parses a JSON string, so `s.parse::<JsonValue>()` works.
+///
+/// There is deliberately no `TryFrom<&str>` or `TryFrom<String>`: the
+/// `From<&str>` and `From<String>` impls that `json!` relies on already wrap
+/// text in [`JsonValue::String`], and the standard library derives an
+/// infallible `TryFrom` from them, so `s.try_into()` never parses. Use
+/// [`JsonValue::try_parse`] for either form instead.
+impl FromStr for JsonValue {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> { JSON::parse(s) }
+}
+
+impl Index<usize> for JsonValue {
+  type Output = JsonValue;
+
+  fn index(&self, index: usize) -> &Self::Output {
+    match self {
+      JsonValue::Array(arr) => &arr[index],
+      _ => panic!("not an array"),
+    }
+  }
+}
+
+impl Index<&str> for JsonValue {
+  type Output = JsonValue;
+
+  fn index(&self, key: &str) -> &Self::Output {
+    match self {
+      JsonValue::Object(obj) => &obj[key],
+      _ => panic!("not an object"),
+    }
+  }
+}
+
+/// Panics if this is not an array or the index is out of bounds, like the
+/// read-only `Index` impl.
+impl IndexMut<usize> for JsonValue {
+  fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+    match self {
+      JsonValue::Array(arr) => &mut arr[index],
+      _ => panic!("not an array"),
+    }
+  }
+}
+
+/// Panics if this is not an object. A missing key is inserted with a `null`
+/// value first, so `value["new"] = ...` adds a field.
+impl IndexMut<&str> for JsonValue {
+  fn index_mut(&mut self, key: &str) -> &mut Self::Output {
+    match self {
+      JsonValue::Object(obj) => {
+        if !obj.contains_key(key) {
+          obj.insert(key.to_string(), JsonValue::Null);
+        }
+        obj.get_mut(key).unwrap()
+      },
+      _ => panic!("not an object"),
+    }
+  }
+}
+
+/// The map type behind [`JsonValue::Object`]. It remembers the order in which
+/// keys were first inserted, so parsing and stringifying a document keeps its
+/// keys where they were.
+///
+/// Lookups go through a hash index and stay O(1) like a `HashMap`. The
+/// tradeoff is that every key is stored twice and removing a key is O(n),
+/// because the entries after it shift down to keep the order. Two maps compare
+/// equal when they hold the same entries, regardless of order.
+#[derive(Debug, Clone, Default)]
+pub struct JsonMap {
+  entries: Vec<(String, JsonValue)>,
+  indices: HashMap<String, usize>,
+}
+
+impl JsonMap {
+  /// Creates an empty map.
+  pub fn new() -> Self { Self::default() }
+
+  /// Returns the number of entries in the map.
+  pub fn len(&self) -> usize { self.entries.len() }
+
+  /// Returns `true` if the map has no entries.
+  pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+  /// Returns `true` if the map contains `key`.
+  pub fn contains_key(&self, key: &str) -> bool { self.indices.contains_key(key) }
+
+  /// Returns the value stored under `key`, if any.
+  pub fn get(&self, key: &str) -> Option<&JsonValue> {
+    self.indices.get(key).map(|&i| &self.entries[i].1)
+  }
+
+  /// Returns a mutable reference to the value stored under `key`, if any.
+  pub fn get_mut(&mut self, key: &str) -> Option<&mut JsonValue> {
+    self.indices.get(key).map(|&i| &mut self.entries[i].1)
+  }
+
+  /// Inserts a value under `key`. A new key is appended at the end; an
+  /// existing key keeps its position and the previous value is returned.
+  pub fn insert(&mut self, key: String, value: JsonValue) -> Option<JsonValue> {
+    match self.indices.get(&key) {
+      Some(&i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+      None => {
+        self.indices.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+        None
+      },
+    }
+  }
+
+  /// Removes `key` from the map and returns its value, keeping the order of
+  /// the remaining entries.
+  pub fn remove(&mut self, key: &str) -> Option<JsonValue> {
+    let i = self.indices.remove(key)?;
+    let (_, value) = self.entries.remove(i);
+    for (k, _) in &self.entries[i..] {
+      *self.indices.get_mut(k).unwrap() -= 1;
+    }
+    Some(value)
+  }
+
+  /// Iterates over the entries in insertion order.
+  pub fn iter(&self) -> JsonMapIter<'_> { JsonMapIter(self.entries.iter()) }
+
+  /// Iterates mutably over the entries in insertion order.
+  pub fn iter_mut(&mut self) -> JsonMapIterMut<'_> { JsonMapIterMut(self.entries.iter_mut()) }
+
+  /// Iterates over the keys in insertion order.
+  pub fn keys(&self) -> impl Iterator<Item = &String> { self.entries.iter().map(|(k, _)| k) }
+
+  /// Iterates over the values in insertion order.
+  pub fn values(&self) -> impl Iterator<Item = &JsonValue> { self.entries.iter().map(|(_, v)| v) }
+}
+
+impl PartialEq for JsonMap {
+  fn eq(&self, other: &Self) -> bool {
+    let mut pairs = vec![];
+    self.len() == other.len()
+      && self.iter().all(|(k, v)| match other.get(k) {
+        Some(w) => {
+          pairs.push((v, w));
+          true
+        },
+        None => false,
+      })
+      && values_eq(pairs)
+  }
+}
+
+impl Index<&str> for JsonMap {
+  type Output = JsonValue;
+
+  fn index(&self, key: &str) -> &Self::Output { self.get(key).expect("key not found") }
+}
+
+impl FromIterator<(String, JsonValue)> for JsonMap {
+  fn from_iter<I: IntoIterator<Item = (String, JsonValue)>>(iter: I) -> Self {
+    let mut map = JsonMap::new();
+    map.extend(iter);
+    map
+  }
+}
+
+impl Extend<(String, JsonValue)> for JsonMap {
+  fn extend<I: IntoIterator<Item = (String, JsonValue)>>(&mut self, iter: I) {
+    for (k, v) in iter {
+      self.insert(k, v);
+    }
+  }
+}
+
+impl<const N: usize> From<[(String, JsonValue); N]> for JsonMap {
+  fn from(arr: [(String, JsonValue); N]) -> Self { arr.into_iter().collect() }
+}
+
+impl From<HashMap<String, JsonValue>> for JsonMap {
+  fn from(map: HashMap<String, JsonValue>) -> Self { map.into_iter().collect() }
+}
+
+impl IntoIterator for JsonMap {
+  type IntoIter = JsonMapIntoIter;
+  type Item = (String, JsonValue);
+
+  fn into_iter(self) -> Self::IntoIter { JsonMapIntoIter(self.entries.into_iter()) }
+}
+
+impl<'a> IntoIterator for &'a JsonMap {
+  type IntoIter = JsonMapIter<'a>;
+  type Item = (&'a String, &'a JsonValue);
+
+  fn into_iter(self) -> Self::IntoIter { self.iter() }
+}
+
+impl<'a> IntoIterator for &'a mut JsonMap {
+  type IntoIter = JsonMapIterMut<'a>;
+  type Item = (&'a String, &'a mut JsonValue);
+
+  fn into_iter(self) -> Self::IntoIter { self.iter_mut() }
+}
+
+/// Iterator over the entries of a [`JsonMap`] in insertion order.
+pub struct JsonMapIter<'a>(std::slice::Iter<'a, (String, JsonValue)>);
+
+impl<'a> Iterator for JsonMapIter<'a> {
+  type Item = (&'a String, &'a JsonValue);
+
+  fn next(&mut self) -> Option<Self::Item> { self.0.next().map(|(k, v)| (k, v)) }
+
+  fn size_hint(&self) -> (usize, Option<usize>) { self.0.size_hint() }
+}
+
+/// Mutable iterator over the entries of a [`JsonMap`] in insertion order.
+pub struct JsonMapIterMut<'a>(std::slice::IterMut<'a, (String, JsonValue)>);
+
+impl<'a> Iterator for JsonMapIterMut<'a> {
+  type Item = (&'a String, &'a mut JsonValue);
+
+  fn next(&mut self) -> Option<Self::Item> { self.0.next().map(|(k, v)| (&*k, v)) }
+
+  fn size_hint(&self) -> (usize, Option<usize>) { self.0.size_hint() }
+}
+
+/// Owning iterator over the entries of a [`JsonMap`] in insertion order.
+pub struct JsonMapIntoIter(std::vec::IntoIter<(String, JsonValue)>);
+
+impl Iterator for JsonMapIntoIter {
+  type Item = (String, JsonValue);
+
+  fn next(&mut self) -> Option<Self::Item> { self.0.next() }
+
+  fn size_hint(&self) -> (usize, Option<usize>) { self.0.size_hint() }
+}
+
+/// The expected shape of a [`JsonValue`], for checking documents such as
+/// config files without a full JSON Schema implementation.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use parsers::{json, json::Schema};
+///
+/// let schema = Schema::Object(HashMap::from([
+///   ("name".to_string(), Schema::String),
+///   ("tags".to_string(), Schema::ArrayOf(Box::new(Schema::String))),
+/// ]));
+/// assert!(schema.validate(&json!({"name": "a", "tags": ["b"]})).is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schema {
+  /// Any value at all.
+  Any,
+  Null,
+  Boolean,
+  /// Any number, integral or not.
+  Number,
+  /// A number without a fractional part.
+  Integer,
+  String,
+  /// An array whose elements all match the inner schema.
+  ArrayOf(Box<Schema>),
+  /// An object that has every listed key, with a value matching its schema.
+  /// Keys that are not listed are allowed.
+  Object(HashMap<String, Schema>),
+  /// Like the inner schema, but as an object member the key may be missing.
+  Optional(Box<Schema>),
+}
+
+impl Schema {
+  /// Checks `value` against this schema.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error for every mismatch, not just the first. The message
+  /// names the JSON Pointer of the mismatching value.
+  pub fn validate(&self, value: &JsonValue) -> Result<(), Vec<Error>> {
+    let mut errors = vec![];
+    self.check(value, &mut String::new(), &mut errors);
+    match errors.is_empty() {
+      true => Ok(()),
+      false => Err(errors),
+    }
+  }
+
+  fn check(&self, value: &JsonValue, path: &mut String, errors: &mut Vec<Error>) {
+    let matches = match (self, value) {
+      (Schema::Any, _) => true,
+      (Schema::Optional(schema), value) => return schema.check(value, path, errors),
+      (Schema::Null, JsonValue::Null)
+      | (Schema::Boolean, JsonValue::Boolean(_))
+      | (Schema::Number, JsonValue::Number(_) | JsonValue::Integer(_))
+      | (Schema::Integer, JsonValue::Integer(_))
+      | (Schema::String, JsonValue::String(_)) => true,
+      (Schema::Integer, JsonValue::Number(n)) => n.fract() == 0.0,
+      (Schema::Number | Schema::Integer, JsonValue::RawNumber(raw)) => {
+        return self.check(&raw_number_value(raw), path, errors)
+      },
+      (Schema::ArrayOf(schema), JsonValue::Array(arr)) => {
+        for (i, element) in arr.iter().enumerate() {
+          let len = path.len();
+          path.push_str(&format!("/{}", i));
+          schema.check(element, path, errors);
+          path.truncate(len);
+        }
+        true
+      },
+      (Schema::Object(members), JsonValue::Object(obj)) => {
+        let mut keys: Vec<_> = members.keys().collect();
+        keys.sort();
+        for key in keys {
+          let schema = &members[key];
+          let len = path.len();
+          path.push('/');
+          path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+          match obj.get(key) {
+            Some(member) => schema.check(member, path, errors),
+            None if matches!(schema, Schema::Optional(_)) => (),
+            None => errors.push(Error::json(format!("Missing key at '{}'", path), 0)),
+          }
+          path.truncate(len);
+        }
+        true
+      },
+      _ => false,
+    };
+    if !matches {
+      let found = match value.type_name() {
+        "null" => "null".to_string(),
+        name @ ("array" | "object") => format!("an {}", name),
+        name => format!("a {}", name),
+      };
+      errors.push(Error::json(
+        format!("Expected {} at '{}', found {}", self.name(), path, found),
+        0,
+      ));
+    }
+  }
+
+  fn name(&self) -> &'static str {
+    match self {
+      Schema::Any => "any value",
+      Schema::Null => "null",
+      Schema::Boolean => "a boolean",
+      Schema::Number => "a number",
+      Schema::Integer => "an integer",
+      Schema::String => "a string",
+      Schema::ArrayOf(_) => "an array",
+      Schema::Object(_) => "an object",
+      Schema::Optional(schema) => schema.name(),
+    }
+  }
+}
+
+#[derive(PartialEq, Clone)]
+enum JsonToken {
+  Null {
+    pos: usize,
+  },
+  String {
+    val: String,
+    pos: usize,
+  },
+  Identifier {
+    val: String,
+    pos: usize,
+  },
+  Number {
+    val: f64,
+    pos: usize,
+  },
+  Integer {
+    val: i64,
+    pos: usize,
+  },
+  RawNumber {
+    val: String,
+    pos: usize,
+  },
+  Boolean {
+    val: bool,
+    pos: usize,
+  },
+  /// Stands in for input the lexer rejected while collecting errors, so
+  /// parsing can resume after it. Its error has already been recorded.
+  Invalid {
+    pos: usize,
+  },
+  Colon {
+    pos: usize,
+  },
+  Comma {
+    pos: usize,
+  },
+  LeftBracket {
+    pos: usize,
+  },
+  RightBracket {
+    pos: usize,
+  },
+  LeftBrace {
+    pos: usize,
+  },
+  RightBrace {
+    pos: usize,
+  },
+  Eof {
+    pos: usize,
+  },
+}
+
+impl JsonToken {
+  fn pos(&self) -> usize {
+    match self {
+      JsonToken::Null { pos }
+      | JsonToken::String { pos, .. }
+      | JsonToken::Identifier { pos, .. }
+      | JsonToken::Number { pos, .. }
+      | JsonToken::Integer { pos, .. }
+      | JsonToken::RawNumber { pos, .. }
+      | JsonToken::Boolean { pos, .. }
+      | JsonToken::Invalid { pos }
+      | JsonToken::Colon { pos }
+      | JsonToken::Comma { pos }
+      | JsonToken::LeftBracket { pos }
+      | JsonToken::RightBracket { pos }
+      | JsonToken::LeftBrace { pos }
+      | JsonToken::RightBrace { pos }
+      | JsonToken::Eof { pos } => *pos,
+    }
+  }
+
+  /// Turns a keyword read where an object key is expected back into the
+  /// identifier it was spelled as, since with `unquoted_keys` JSON5 also
+  /// accepts `null`, `true`, `false`, `NaN` and `Infinity` as keys. `json`
+  /// is the input the token was read from.
+  fn keyword_as_key(self, json: &str, unquoted_keys: bool) -> JsonToken {
+    match self {
+      JsonToken::Null { pos } | JsonToken::Boolean { pos, .. } | JsonToken::Number { pos, .. }
+        if unquoted_keys && json[pos..].starts_with(char::is_alphabetic) =>
+      {
+        let word = &json[pos..];
+        let len = word
+          .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+          .unwrap_or(word.len());
+        JsonToken::Identifier {
+          val: word[..len].to_string(),
+          pos,
+        }
+      },
+      token => token,
+    }
+  }
+}
+
+/// Checks a number literal against the RFC 8259 grammar, which is
+/// `-? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?`. On failure returns
+/// the byte offset of the offending character within `s` and the reason.
+///
+/// With `lenient` set, a leading `+` is allowed and either side of the decimal
+/// point may be empty, as in JSON5's `+1`, `.5` and `5.`.
+fn check_json_number(s: &str, lenient: bool) -> Result<(), (usize, &'static str)> {
+  let bytes = s.as_bytes();
+  let digits = |mut i: usize| {
+    while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+      i += 1;
+    }
+    i
+  };
+  let mut i = 0;
+  match bytes.first() {
+    Some(b'-') => i += 1,
+    Some(b'+') if lenient => i += 1,
+    _ => (),
+  }
+  let integer = i;
+  match bytes.get(i) {
+    Some(b'0') if bytes.get(i + 1).is_some_and(u8::is_ascii_digit) => {
+      return Err((i, "leading zeros are not allowed"))
+    },
+    Some(b'0') => i += 1,
+    Some(b'1'..=b'9') => i = digits(i),
+    Some(b'.') if lenient && bytes.get(i + 1).is_some_and(u8::is_ascii_digit) => (),
+    _ => return Err((i, "expected a digit")),
+  }
+  if bytes.get(i) == Some(&b'.') {
+    let end = digits(i + 1);
+    if end == i + 1 && !(lenient && i > integer) {
+      return Err((end, "expected a digit after '.'"));
+    }
+    i = end;
+  }
+  if matches!(bytes.get(i), Some(b'e' | b'E')) {
+    i += 1;
+    if matches!(bytes.get(i), Some(b'+' | b'-')) {
+      i += 1;
+    }
+    let end = digits(i);
+    if end == i {
+      return Err((end, "expected a digit in the exponent"));
+    }
+    i = end;
+  }
+  match i == bytes.len() {
+    true => Ok(()),
+    false => Err((i, "unexpected character")),
+  }
+}
+
+/// Tokenizer over a borrowed JSON string. `index` is a byte offset into
+/// `json` that always sits on a char boundary, so looking at the current
+/// character is constant time.
+struct JsonLexer<'a> {
+  json:    &'a str,
+  options: ParseOptions,
+  index:   usize,
+}
+
+impl<'a> JsonLexer<'a> {
+  pub fn new(json: &'a str, options: ParseOptions) -> Self {
+    Self {
+      json,
+      options,
+      index: 0,
+    }
+  }
+
+  fn advance(&mut self) -> Option<char> {
+    if let Some(c) = self.current() {
+      self.index += c.len_utf8();
+    }
+    self.current()
+  }
+
+  fn current(&self) -> Option<char> { self.json[self.index..].chars().next() }
+
+  fn peek(&self) -> Option<char> {
+    let mut chars = self.json[self.index..].chars();
+    chars.next();
+    chars.next()
+  }
+
+  /// Skips a UTF-8 byte order mark, as some Windows tools write at the start
+  /// of a file. Only call this at the start of the input: anywhere else a
+  /// byte order mark is an unexpected character.
+  fn skip_bom(&mut self) {
+    if self.index == 0 && self.current() == Some('\u{feff}') {
+      self.advance();
+    }
+  }
+
+  fn make_string(&mut self) -> Result<JsonToken, Error> {
+    let start = self.index;
+    let quote = self.current();
+    let mut result = String::new();
+    loop {
+      let Some(c) = self.advance() else {
+        return Err(
+          Error::json("Unterminated string".to_string(), start)
+            .with_kind(ErrorKind::UnterminatedString),
+        );
+      };
+      match c {
+        c if Some(c) == quote => {
+          self.advance();
+          return Ok(JsonToken::String {
+            val: result,
+            pos: start,
+          });
+        },
+        '\\' => match self.advance() {
+          Some('"') => result.push('"'),
+          Some('\'') if self.options.single_quotes => result.push('\''),
+          Some('\\') => result.push('\\'),
+          Some('/') => result.push('/'),
+          Some('b') => result.push('\x08'),
+          Some('f') => result.push('\x0C'),
+          Some('n') => result.push('\n'),
+          Some('r') => result.push('\r'),
+          Some('t') => result.push('\t'),
+          Some('u') => {
+            let c = self.make_unicode_char(self.index - 1)?;
+            result.push(c);
+          },
+          Some(c) => {
+            return Err(
+              Error::json(format!("Invalid escape sequence '\\{}'", c), self.index - 1)
+                .with_kind(ErrorKind::InvalidEscape),
+            )
+          },
+          None => {
+            return Err(
+              Error::json("Unexpected end of string".to_string(), self.index)
+                .with_kind(ErrorKind::UnterminatedString),
+            )
+          },
+        },
+        c if c < '\u{20}' && !self.options.control_characters => {
+          return Err(
+            Error::json("Unescaped control character".to_string(), self.index)
+              .with_kind(ErrorKind::UnexpectedChar),
+          )
+        },
+        _ => result.push(c),
+      }
+      if self
+        .options
+        .max_string_len
+        .is_some_and(|max| result.len() > max)
+      {
+        return Err(
+          Error::json("String exceeds maximum length".to_string(), start)
+            .with_kind(ErrorKind::LimitExceeded),
+        );
+      }
+    }
+  }
+
+  fn make_unicode_escape(&mut self, pos: usize) -> Result<u16, Error> {
+    let mut digits = String::new();
+    for _ in 0..4 {
+      match self.advance() {
+        Some(c) if c.is_ascii_hexdigit() => digits.push(c),
+        _ => {
+          return Err(
+            Error::json("Invalid unicode escape".to_string(), pos)
+              .with_kind(ErrorKind::InvalidEscape),
+          )
+        },
+      }
+    }
+    Ok(u16::from_str_radix(&digits, 16).unwrap())
+  }
+
+  fn make_unicode_char(&mut self, pos: usize) -> Result<char, Error> {
+    let code = self.make_unicode_escape(pos)? as u32;
+    match code {
+      0xd800..=0xdbff => {
+        if self.peek() != Some('\\') {
+          return Err(
+            Error::json("Unpaired high surrogate".to_string(), pos)
+              .with_kind(ErrorKind::InvalidEscape),
+          );
+        }
+        let next = self.index + 1;
+        self.advance();
+        if self.advance() != Some('u') {
+          return Err(
+            Error::json(
+              "Expected low surrogate after high surrogate".to_string(),
+              next,
+            )
+            .with_kind(ErrorKind::InvalidEscape),
+          );
+        }
+        let low = self.make_unicode_escape(next)? as u32;
+        if !(0xdc00..=0xdfff).contains(&low) {
+          return Err(
+            Error::json(
+              "Expected low surrogate after high surrogate".to_string(),
+              next,
+            )
+            .with_kind(ErrorKind::InvalidEscape),
+          );
+        }
+        Ok(char::from_u32(0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00)).unwrap())
+      },
+      0xdc00..=0xdfff => Err(
+        Error::json("Unpaired low surrogate".to_string(), pos).with_kind(ErrorKind::InvalidEscape),
+      ),
+      _ => Ok(char::from_u32(code).unwrap()),
+    }
+  }
+
+  fn make_number(&mut self) -> Result<JsonToken, Error> {
+    let start = self.index;
+    if self.options.hex_numbers {
+      let sign = matches!(self.current(), Some('-' | '+')) as usize;
+      let prefix = self.json[start + sign..].get(..2);
+      if matches!(prefix, Some("0x" | "0X")) {
+        return self.make_hex_number(start + sign);
+      }
+    }
+    let mut result = String::new();
+    while let Some(c) = self.current() {
+      match c {
+        '0'..='9' | '.' | '-' | '+' | 'e' | 'E' => {
+          result.push(c);
+          self.advance();
+        },
+        _ => break,
+      }
+    }
+    if let Err((offset, reason)) = check_json_number(&result, self.options.lenient_numbers) {
+      return Err(
+        Error::json(
+          format!("Invalid number '{}': {}", result, reason),
+          start + offset,
+        )
+        .with_kind(ErrorKind::InvalidNumber),
+      );
+    }
+    // Literals relaxed by `lenient_numbers` are not valid JSON, so they are
+    // never kept raw.
+    if self.options.number_mode == NumberMode::Raw && check_json_number(&result, false).is_ok() {
+      return Ok(JsonToken::RawNumber {
+        val: result,
+        pos: start,
+      });
+    }
+    if !result.contains(['.', 'e', 'E']) && result != "-0" {
+      if let Ok(n) = result.parse::<i64>() {
+        return Ok(JsonToken::Integer { val: n, pos: start });
+      }
+    }
+    match result.parse::<f64>() {
+      Ok(n) => Ok(JsonToken::Number { val: n, pos: start }),
+      Err(_) => Err(
+        Error::json(format!("Invalid number '{}'", result), start)
+          .with_kind(ErrorKind::InvalidNumber),
+      ),
+    }
+  }
+
+  /// Lexes a `0x` integer whose prefix starts at `prefix`, with the sign, if
+  /// any, at the current position.
+  fn make_hex_number(&mut self, prefix: usize) -> Result<JsonToken, Error> {
+    let start = self.index;
+    let negative = self.current() == Some('-');
+    self.index = prefix + 2;
+    let digits = self.index;
+    while self.current().is_some_and(|c| c.is_ascii_alphanumeric()) {
+      self.advance();
+    }
+    let literal = &self.json[start..self.index];
+    match &self.json[digits..self.index] {
+      "" => Err(
+        Error::json(
+          format!("Invalid number '{}': expected a hex digit", literal),
+          digits,
+        )
+        .with_kind(ErrorKind::InvalidNumber),
+      ),
+      hex => match i64::from_str_radix(hex, 16) {
+        Ok(n) if negative => Ok(JsonToken::Integer {
+          val: -n,
+          pos: start,
+        }),
+        Ok(n) => Ok(JsonToken::Integer { val: n, pos: start }),
+        Err(_) => Err(
+          Error::json(format!("Invalid number '{}'", literal), start)
+            .with_kind(ErrorKind::InvalidNumber),
+        ),
+      },
+    }
+  }
+
+  /// Whether `c` can appear in an unquoted object key, given whether it is the
+  /// first character of the key.
+  fn is_identifier_char(&self, c: char, first: bool) -> bool {
+    self.options.unquoted_keys
+      && (c.is_alphabetic() || c == '_' || c == '$' || !first && c.is_numeric())
+  }
+
+  fn make_keyword(&mut self) -> Result<JsonToken, Error> {
+    let start = self.index;
+    let mut result = String::new();
+    while let Some(c) = self.current() {
+      match c {
+        'a'..='z' | 'A'..='Z' => {
+          result.push(c);
+          self.advance();
+        },
+        '-' | '+' if result.is_empty() => {
+          result.push(c);
+          self.advance();
+        },
+        c if self.is_identifier_char(c, result.is_empty()) => {
+          result.push(c);
+          self.advance();
+        },
+        _ => break,
+      }
+    }
+    let number = |val| Ok(JsonToken::Number { val, pos: start });
+    let signed_nan = self.options.allow_nan && self.options.lenient_numbers;
+    match result.as_str() {
+      "NaN" if self.options.allow_nan => number(f64::NAN),
+      "Infinity" if self.options.allow_nan => number(f64::INFINITY),
+      "-Infinity" if self.options.allow_nan => number(f64::NEG_INFINITY),
+      "+Infinity" if signed_nan => number(f64::INFINITY),
+      "-NaN" | "+NaN" if signed_nan => number(f64::NAN),
+      "null" => Ok(JsonToken::Null { pos: start }),
+      "true" => Ok(JsonToken::Boolean {
+        val: true,
+        pos: start,
+      }),
+      "false" => Ok(JsonToken::Boolean {
+        val: false,
+        pos: start,
+      }),
+      _ if self.options.unquoted_keys && !result.starts_with(['-', '+']) => {
+        Ok(JsonToken::Identifier {
+          val: result,
+          pos: start,
+        })
+      },
+      _ => Err(
+        Error::json(format!("Unexpected '{}'", result), start)
+          .with_kind(ErrorKind::UnexpectedToken),
+      ),
+    }
+  }
+
+  fn make_symbol(&mut self) -> Result<JsonToken, Error> {
+    let start = self.index;
+    let result = match self.current() {
+      Some(':') => JsonToken::Colon { pos: start },
+      Some(',') => JsonToken::Comma { pos: start },
+      Some('[') => JsonToken::LeftBracket { pos: start },
+      Some(']') => JsonToken::RightBracket { pos: start },
+      Some('{') => JsonToken::LeftBrace { pos: start },
+      Some('}') => JsonToken::RightBrace { pos: start },
+      Some(c) => {
+        return Err(
+          Error::json(format!("Unexpected '{}'", c), start).with_kind(ErrorKind::UnexpectedChar),
+        )
+      },
+      None => JsonToken::Eof { pos: start },
+    };
+    self.advance();
+    Ok(result)
+  }
+
+  fn skip_comment(&mut self) -> Result<(), Error> {
+    let start = self.index;
+    match self.advance() {
+      Some('/') => {
+        while !matches!(self.advance(), Some('\n') | None) {}
+        Ok(())
+      },
+      Some('*') => {
+        self.advance();
+        loop {
+          match self.current() {
+            Some('*') if self.peek() == Some('/') => {
+              self.advance();
+              self.advance();
+              return Ok(());
+            },
+            Some(_) => {
+              self.advance();
+            },
+            None => {
+              return Err(
+                Error::json("Unterminated block comment".to_string(), start)
+                  .with_kind(ErrorKind::UnexpectedEof),
+              )
+            },
+          }
+        }
+      },
+      _ => {
+        Err(Error::json("Unexpected '/'".to_string(), start).with_kind(ErrorKind::UnexpectedChar))
+      },
+    }
+  }
+
+  /// Skips the rest of an invalid token starting at `start`, up to the next
+  /// whitespace or punctuation, so lexing can resume after an error.
+  fn skip_invalid(&mut self, start: usize) {
+    if self.index <= start {
+      self.advance();
+    }
+    while !matches!(
+      self.current(),
+      None | Some(' ' | '\t' | '\n' | '\r' | ',' | ':' | '[' | ']' | '{' | '}')
+    ) {
+      self.advance();
+    }
+  }
+
+  pub fn next_token(&mut self) -> Result<JsonToken, Error> {
+    loop {
+      match self.current() {
+        Some(' ' | '\t' | '\n' | '\r') => {
+          self.advance();
+        },
+        Some('/') if self.options.comments => self.skip_comment()?,
+        _ => break,
+      }
+    }
+    match self.current() {
+      None => Ok(JsonToken::Eof { pos: self.index }),
+      Some('"') => self.make_string(),
+      Some('\'') if self.options.single_quotes => self.make_string(),
+      Some('-' | '+') if self.options.allow_nan && matches!(self.peek(), Some('I' | 'N')) => {
+        self.make_keyword()
+      },
+      Some('0'..='9' | '-' | '.') => self.make_number(),
+      Some('+') if self.options.lenient_numbers => self.make_number(),
+      Some('f'..='t' | 'I' | 'N') => self.make_keyword(),
+      Some(c) if self.is_identifier_char(c, true) => self.make_keyword(),
+      Some(':' | ',' | '[' | ']' | '{' | '}') => self.make_symbol(),
+      Some(c) => Err(
+        Error::json(format!("Unexpected '{}'", c), self.index).with_kind(ErrorKind::UnexpectedChar),
+      ),
+    }
+  }
+
+  pub fn lex(&mut self) -> Result<Vec<JsonToken>, Error> {
+    let mut result = vec![];
+    loop {
+      let token = self.next_token()?;
+      let eof = matches!(token, JsonToken::Eof { .. });
+      result.push(token);
+      if eof {
+        return Ok(result);
+      }
+    }
+  }
+}
+
+/// An array or object that [`JsonParser`] is in the middle of.
+enum Container {
+  Array(Vec<JsonValue>),
+  /// The members so far, and the key of the member whose value is being
+  /// parsed along with whether to store it.
+  Object(JsonMap, Option<(String, bool)>),
+}
+
+struct Frame {
+  container: Container,
+  /// Position of the comma after the last element or member, if any.
+  comma:     Option<usize>,
+}
+
+impl Frame {
+  fn is_object(&self) -> bool { matches!(self.container, Container::Object(..)) }
+}
+
+/// What [`JsonParser::parse_value`] does next.
+enum Step {
+  /// Parse the value at the current token.
+  Value,
+  /// Read the next element or member of the innermost container, or its end.
+  Next,
+  /// Read the comma or closing bracket after an element or member.
+  Separator,
+  /// Recover from an error in the value of the innermost container.
+  Recover(Error),
+  /// Finish the innermost container.
+  Close,
+  /// Hand a finished value to the innermost container.
+  Done(JsonValue),
+}
+
+struct JsonParser<'a> {
+  json:     &'a str,
+  options:  ParseOptions,
+  tokens:   Vec<JsonToken>,
+  index:    usize,
+  /// How many values have been started so far.
+  elements: usize,
+  /// Whether `json` is the start of the input, which may begin with a byte
+  /// order mark.
+  at_start: bool,
+  /// Whether to record errors in `errors` and carry on instead of failing.
+  collect:  bool,
+  errors:   Vec<Error>,
+}
+
+impl<'a> JsonParser<'a> {
+  pub fn new(json: &'a str, options: ParseOptions) -> Self {
+    Self {
+      json,
+      options,
+      tokens: vec![],
+      index: 0,
+      elements: 0,
+      at_start: true,
+      collect: false,
+      errors: vec![],
+    }
+  }
+
+  fn lexer(&self) -> JsonLexer<'a> {
+    let mut lexer = JsonLexer::new(self.json, self.options);
+    if self.at_start {
+      lexer.skip_bom();
+    }
+    lexer
+  }
+
+  fn advance(&mut self) -> Option<JsonToken> {
+    self.index += 1;
+    self.tokens.get(self.index).cloned()
+  }
+
+  fn current(&self) -> Option<JsonToken> { self.tokens.get(self.index).cloned() }
+
+  /// Fails with `error`, or records it and carries on when collecting errors.
+  fn report(&mut self, error: Error) -> Result<(), Error> {
+    if !self.collect {
+      return Err(error);
+    }
+    self.errors.push(error);
+    Ok(())
+  }
+
+  /// Reports `error` for the current token. When collecting errors, skips
+  /// ahead to the next `,`, `]` or `}` of the enclosing container so the next
+  /// `advance` returns it. A closing bracket that does not match the enclosing
+  /// container is skipped too. Running out of input cannot be recovered from.
+  fn recover(&mut self, error: Error, is_object: bool) -> Result<(), Error> {
+    if matches!(self.current(), Some(JsonToken::Eof { .. }) | None) {
+      return Err(error);
+    }
+    if !self.at_invalid() {
+      self.report(error)?;
+    }
+    let start = self.index;
+    let mut depth = 0_usize;
+    loop {
+      match self.tokens[self.index] {
+        JsonToken::Eof { .. } => break,
+        JsonToken::Comma { .. } if depth == 0 => break,
+        JsonToken::RightBracket { .. } if depth == 0 && (self.index > start || !is_object) => break,
+        JsonToken::RightBrace { .. } if depth == 0 && (self.index > start || is_object) => break,
+        JsonToken::RightBracket { .. } | JsonToken::RightBrace { .. } => {
+          depth = depth.saturating_sub(1)
+        },
+        JsonToken::LeftBracket { .. } | JsonToken::LeftBrace { .. } => depth += 1,
+        _ => (),
+      }
+      self.index += 1;
+    }
+    self.index -= 1;
+    Ok(())
+  }
+
+  fn at_invalid(&self) -> bool { matches!(self.current(), Some(JsonToken::Invalid { .. })) }
+
+  /// Consumes the token after an array element or object member. Returns the
+  /// position of the comma, or `None` if the container was closed.
+  fn parse_separator(&mut self, is_object: bool) -> Result<Option<usize>, Error> {
+    loop {
+      match self.advance() {
+        Some(JsonToken::Comma { pos }) => return Ok(Some(pos)),
+        Some(JsonToken::RightBracket { .. }) if !is_object => return Ok(None),
+        Some(JsonToken::RightBrace { .. }) if is_object => return Ok(None),
+        Some(JsonToken::Eof { pos }) => return Err(Self::unexpected_eof(pos)),
+        Some(token) => {
+          let message = match is_object {
+            true => "Expected ',' or '}'",
+            false => "Expected ',' or ']'",
+          };
+          self.recover(
+            Error::json(message.to_string(), token.pos()).with_kind(ErrorKind::UnexpectedToken),
+            is_object,
+          )?
+        },
+        None => unreachable!(),
+      }
+    }
+  }
+
+  /// Parses the `:` after the member key `key` at `pos` and moves to the
+  /// first token of the value. Returns whether the value should be stored,
+  /// which it should not if the key is a duplicate.
+  fn parse_member(&mut self, result: &JsonMap, key: &str, pos: usize) -> Result<bool, Error> {
+    match self.advance() {
+      Some(JsonToken::Colon { .. }) => (),
+      Some(JsonToken::Eof { pos }) => return Err(Self::unexpected_eof(pos)),
+      Some(token) => {
+        return Err(
+          Error::json("Expected ':'".to_string(), token.pos())
+            .with_kind(ErrorKind::UnexpectedToken),
+        )
+      },
+      None => unreachable!(),
+    }
+    let duplicate = result.contains_key(key);
+    if duplicate && self.options.duplicate_keys == DuplicateKeyPolicy::Error {
+      return Err(
+        Error::json(format!("Duplicate key '{}'", key), pos).with_kind(ErrorKind::DuplicateKey),
+      );
+    }
+    self.advance();
+    Ok(!duplicate || self.options.duplicate_keys == DuplicateKeyPolicy::Last)
+  }
+
+  fn unexpected_eof(pos: usize) -> Error {
+    Error::json("Unexpected end of input".to_string(), pos).with_kind(ErrorKind::UnexpectedEof)
+  }
+
+  /// Fails if a container is being closed right after the comma at `comma`,
+  /// unless trailing commas are allowed.
+  fn check_trailing_comma(&mut self, comma: Option<usize>) -> Result<(), Error> {
+    match comma {
+      Some(pos) if !self.options.trailing_commas => self.report(
+        Error::json("Trailing comma is not allowed".to_string(), pos)
+          .with_kind(ErrorKind::UnexpectedToken),
+      ),
+      _ => Ok(()),
+    }
+  }
+
+  /// Parses the scalar at the current token, or opens the array or object
+  /// it starts by pushing a frame onto `stack`.
+  fn start_value(&mut self, stack: &mut Vec<Frame>) -> Result<Option<JsonValue>, Error> {
+    let val = match self.current() {
+      Some(JsonToken::String { val, .. }) => JsonValue::String(val),
+      Some(JsonToken::Number { val, .. }) => JsonValue::Number(val),
+      Some(JsonToken::Integer { val, .. }) => JsonValue::Integer(val),
+      Some(JsonToken::RawNumber { val, .. }) => JsonValue::RawNumber(val),
+      Some(JsonToken::Boolean { val, .. }) => JsonValue::Boolean(val),
+      Some(JsonToken::Null { .. }) => JsonValue::Null,
+      Some(JsonToken::Identifier { val, pos }) => {
+        return Err(
+          Error::json(format!("Unexpected '{}'", val), pos).with_kind(ErrorKind::UnexpectedToken),
+        )
+      },
+      Some(JsonToken::LeftBrace { pos }) | Some(JsonToken::LeftBracket { pos }) => {
+        if stack.len() >= self.options.max_depth {
+          return Err(
+            Error::json("Maximum nesting depth exceeded".to_string(), pos)
+              .with_kind(ErrorKind::DepthExceeded),
+          );
+        }
+        let container = match self.current() {
+          Some(JsonToken::LeftBrace { .. }) => Container::Object(JsonMap::new(), None),
+          _ => Container::Array(vec![]),
+        };
+        stack.push(Frame {
+          container,
+          comma: None,
+        });
+        return Ok(None);
+      },
+      Some(JsonToken::Colon { pos }) => {
+        return Err(
+          Error::json("Unexpected ':'".to_string(), pos).with_kind(ErrorKind::UnexpectedToken),
+        )
+      },
+      Some(JsonToken::Comma { pos }) => {
+        return Err(
+          Error::json("Unexpected ','".to_string(), pos).with_kind(ErrorKind::UnexpectedToken),
+        )
+      },
+      Some(JsonToken::RightBrace { pos }) => {
+        return Err(
+          Error::json("Unexpected '}'".to_string(), pos).with_kind(ErrorKind::UnexpectedToken),
+        )
+      },
+      Some(JsonToken::RightBracket { pos }) => {
+        return Err(
+          Error::json("Unexpected ']'".to_string(), pos).with_kind(ErrorKind::UnexpectedToken),
+        )
+      },
+      Some(JsonToken::Eof { pos }) => return Err(Self::unexpected_eof(pos)),
+      Some(JsonToken::Invalid { pos }) => {
+        return Err(
+          Error::json("Invalid token".to_string(), pos).with_kind(ErrorKind::UnexpectedToken),
+        )
+      },
+      None => unreachable!(),
+    };
+
+    Ok(Some(val))
+  }
+
+  /// Reads the token after `[`, `{` or a comma of the innermost container:
+  /// its end, or the start of its next element or member.
+  fn parse_next(&mut self, frame: &mut Frame) -> Result<Step, Error> {
+    let mut token = self.advance().unwrap();
+    if let Container::Object(..) = frame.container {
+      token = token.keyword_as_key(self.json, self.options.unquoted_keys);
+    }
+    match (&mut frame.container, token) {
+      (Container::Array(_), JsonToken::RightBracket { .. })
+      | (Container::Object(..), JsonToken::RightBrace { .. }) => {
+        self.check_trailing_comma(frame.comma)?;
+        Ok(Step::Close)
+      },
+      (Container::Array(_), JsonToken::Colon { pos } | JsonToken::Comma { pos }) => {
+        Ok(Step::Recover(
+          Error::json("Expected a value".to_string(), pos).with_kind(ErrorKind::UnexpectedToken),
+        ))
+      },
+      (Container::Array(_), _) => Ok(Step::Value),
+      (
+        Container::Object(result, key),
+        JsonToken::String { val, pos } | JsonToken::Identifier { val, pos },
+      ) => match self.parse_member(result, &val, pos) {
+        Ok(store) => {
+          *key = Some((val, store));
+          Ok(Step::Value)
+        },
+        Err(error) => Ok(Step::Recover(error)),
+      },
+      (Container::Object(..), JsonToken::Eof { pos }) => Err(Self::unexpected_eof(pos)),
+      (Container::Object(..), token) => Ok(Step::Recover(
+        Error::json("Expected string".to_string(), token.pos())
+          .with_kind(ErrorKind::UnexpectedToken),
+      )),
+    }
+  }
+
+  /// Parses the value at the current token. Arrays and objects are tracked
+  /// on an explicit stack rather than by recursion, so nesting is only
+  /// limited by `max_depth` and memory.
+  fn parse_value(&mut self) -> Result<JsonValue, Error> {
+    let mut stack: Vec<Frame> = vec![];
+    let mut step = Step::Value;
+    loop {
+      step = match step {
+        Step::Value => {
+          self.elements += 1;
+          if self
+            .options
+            .max_elements
+            .is_some_and(|max| self.elements > max)
+          {
+            return Err(
+              Error::json(
+                "Maximum number of elements exceeded".to_string(),
+                self.current().unwrap().pos(),
+              )
+              .with_kind(ErrorKind::LimitExceeded),
+            );
+          }
+          match self.start_value(&mut stack) {
+            Ok(Some(value)) => Step::Done(value),
+            Ok(None) => Step::Next,
+            Err(error) => Step::Recover(error),
+          }
+        },
+        Step::Next => self.parse_next(stack.last_mut().unwrap())?,
+        Step::Separator => {
+          let frame = stack.last_mut().unwrap();
+          match self.parse_separator(frame.is_object())? {
+            Some(pos) => {
+              frame.comma = Some(pos);
+              Step::Next
+            },
+            None => Step::Close,
+          }
+        },
+        Step::Recover(error) => match stack.last() {
+          Some(frame) => {
+            self.recover(error, frame.is_object())?;
+            Step::Separator
+          },
+          None => return Err(error),
+        },
+        Step::Close => match stack.pop().unwrap().container {
+          Container::Array(result) => Step::Done(JsonValue::Array(result)),
+          Container::Object(result, _) => Step::Done(JsonValue::Object(result)),
+        },
+        Step::Done(value) => match stack.last_mut() {
+          Some(Frame {
+            container: Container::Array(result),
+            ..
+          }) => {
+            result.push(value);
+            Step::Separator
+          },
+          Some(Frame {
+            container: Container::Object(result, key),
+            ..
+          }) => {
+            if let Some((key, true)) = key.take() {
+              result.insert(key, value);
+            }
+            Step::Separator
+          },
+          None => return Ok(value),
+        },
+      };
+    }
+  }
+
+  pub fn parse(&mut self) -> Result<JsonValue, Error> {
+    let json = self.json;
+    self.parse_document().map_err(|error| error.locate(json))
+  }
+
+  /// Parses the document like `parse`, but records errors and resumes after
+  /// them instead of stopping at the first one. Returns the value unless the
+  /// error could not be recovered from, along with every error in source
+  /// order.
+  pub fn parse_collect(&mut self) -> (Option<JsonValue>, Vec<Error>) {
+    self.collect = true;
+    let mut lexer = self.lexer();
+    loop {
+      match lexer.next_token() {
+        Ok(token) => {
+          let eof = matches!(token, JsonToken::Eof { .. });
+          self.tokens.push(token);
+          if eof {
+            break;
+          }
+        },
+        Err(error) => {
+          lexer.skip_invalid(error.index);
+          self.tokens.push(JsonToken::Invalid { pos: error.index });
+          self.errors.push(error);
+        },
+      }
+    }
+    let value = match self.parse_value() {
+      Ok(value) => {
+        if let Some(token) = self
+          .advance()
+          .filter(|token| !matches!(token, JsonToken::Eof { .. }))
+        {
+          self.errors.push(
+            Error::json("Unexpected trailing content".to_string(), token.pos())
+              .with_kind(ErrorKind::TrailingContent),
+          );
+        }
+        Some(value)
+      },
+      Err(error) => {
+        if !self.at_invalid() {
+          self.errors.push(error);
+        }
+        None
+      },
+    };
+    let mut errors = std::mem::take(&mut self.errors);
+    errors.sort_by_key(|error| error.index);
+    let errors = errors
+      .into_iter()
+      .map(|error| error.locate(self.json))
+      .collect();
+    (value, errors)
+  }
+
+  /// Parses the first value of the input and returns it along with the byte
+  /// offset just past it. Whatever follows the value is never lexed.
+  pub fn parse_partial(&mut self) -> Result<(JsonValue, usize), Error> {
+    let json = self.json;
+    self.parse_prefix().map_err(|error| error.locate(json))
+  }
+
+  fn parse_prefix(&mut self) -> Result<(JsonValue, usize), Error> {
+    let mut lexer = self.lexer();
+    let mut depth = 0_usize;
+    loop {
+      let token = lexer.next_token()?;
+      match token {
+        JsonToken::LeftBrace { .. } | JsonToken::LeftBracket { .. } => depth += 1,
+        JsonToken::RightBrace { .. } | JsonToken::RightBracket { .. } => {
+          depth = depth.saturating_sub(1)
+        },
+        _ => (),
+      }
+      let eof = matches!(token, JsonToken::Eof { .. });
+      self.tokens.push(token);
+      if eof || depth == 0 {
+        break;
+      }
+    }
+    // Stand in for the rest of the input, should the value end early.
+    self.tokens.push(JsonToken::Eof { pos: lexer.index });
+    let value = self.parse_value()?;
+    Ok((value, lexer.index))
+  }
+
+  fn parse_document(&mut self) -> Result<JsonValue, Error> {
+    let mut lexer = self.lexer();
+    self.tokens = lexer.lex()?;
+    let value = self.parse_value()?;
+    match self.advance() {
+      Some(JsonToken::Eof { .. }) | None => Ok(value),
+      Some(token) => Err(
+        Error::json("Unexpected trailing content".to_string(), token.pos())
+          .with_kind(ErrorKind::TrailingContent),
+      ),
+    }
+  }
+}
+
+/// An event produced by [`JsonEvents`] while walking a document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonEvent {
+  StartObject,
+  /// An object key. The key's value follows as the next event.
+  Key(String),
+  EndObject,
+  StartArray,
+  EndArray,
+  /// A scalar value: null, a boolean, a number or a string.
+  Value(JsonValue),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EventState {
+  Value,
+  FirstElement,
+  AfterElement,
+  FirstKey,
+  Key,
+  Colon,
+  AfterEntry,
+  End,
+  Done,
+}
+
+/// Pull-based reader that walks a JSON document token by token and yields
+/// [`JsonEvent`]s without building a [`JsonValue`] tree. Created with
+/// [`JSON::events`].
+///
+/// Only scalars are materialized, so huge arrays can be filtered or
+/// transformed with memory proportional to their nesting depth. Duplicate
+/// object keys are not detected, since that would mean remembering every key.
+/// After an error the iterator is finished.
+pub struct JsonEvents<'a> {
+  lexer: JsonLexer<'a>,
+  stack: Vec<bool>,
+  state: EventState,
+}
+
+impl<'a> JsonEvents<'a> {
+  fn new(json: &'a str, options: ParseOptions) -> Self {
+    let mut lexer = JsonLexer::new(json, options);
+    lexer.skip_bom();
+    Self {
+      lexer,
+      stack: vec![],
+      state: EventState::Value,
+    }
+  }
+
+  fn after_value(&self) -> EventState {
+    match self.stack.last() {
+      None => EventState::End,
+      Some(false) => EventState::AfterElement,
+      Some(true) => EventState::AfterEntry,
+    }
+  }
+
+  fn open(&mut self, is_object: bool, pos: usize) -> Result<JsonEvent, Error> {
+    if self.stack.len() >= self.lexer.options.max_depth {
+      return Err(
+        Error::json("Maximum nesting depth exceeded".to_string(), pos)
+          .with_kind(ErrorKind::DepthExceeded),
+      );
+    }
+    self.stack.push(is_object);
+    match is_object {
+      true => {
+        self.state = EventState::FirstKey;
+        Ok(JsonEvent::StartObject)
+      },
+      false => {
+        self.state = EventState::FirstElement;
+        Ok(JsonEvent::StartArray)
+      },
+    }
+  }
+
+  fn value(&mut self, value: JsonValue) -> JsonEvent {
+    self.state = self.after_value();
+    JsonEvent::Value(value)
+  }
+
+  fn close(&mut self) -> JsonEvent {
+    let is_object = self.stack.pop().unwrap();
+    self.state = self.after_value();
+    match is_object {
+      true => JsonEvent::EndObject,
+      false => JsonEvent::EndArray,
+    }
+  }
+
+  fn next_event(&mut self) -> Result<Option<JsonEvent>, Error> {
+    use EventState::*;
+    loop {
+      let mut token = self.lexer.next_token()?;
+      let pos = token.pos();
+      if let FirstKey | Key = self.state {
+        token = token.keyword_as_key(self.lexer.json, self.lexer.options.unquoted_keys);
+      }
+      match (self.state, token) {
+        (Value | FirstElement, JsonToken::Null { .. }) => {
+          return Ok(Some(self.value(JsonValue::Null)))
+        },
+        (Value | FirstElement, JsonToken::Boolean { val, .. }) => {
+          return Ok(Some(self.value(JsonValue::Boolean(val))))
+        },
+        (Value | FirstElement, JsonToken::Number { val, .. }) => {
+          return Ok(Some(self.value(JsonValue::Number(val))))
+        },
+        (Value | FirstElement, JsonToken::Integer { val, .. }) => {
+          return Ok(Some(self.value(JsonValue::Integer(val))))
+        },
+        (Value | FirstElement, JsonToken::RawNumber { val, .. }) => {
+          return Ok(Some(self.value(JsonValue::RawNumber(val))))
+        },
+        (Value | FirstElement, JsonToken::String { val, .. }) => {
+          return Ok(Some(self.value(JsonValue::String(val))))
+        },
+        (Value | FirstElement, JsonToken::Identifier { val, pos }) => {
+          return Err(
+            Error::json(format!("Unexpected '{}'", val), pos).with_kind(ErrorKind::UnexpectedToken),
+          )
+        },
+        (Value | FirstElement, JsonToken::LeftBracket { pos }) => {
+          return self.open(false, pos).map(Some)
+        },
+        (Value | FirstElement, JsonToken::LeftBrace { pos }) => {
+          return self.open(true, pos).map(Some)
+        },
+        (FirstElement, JsonToken::RightBracket { .. })
+        | (AfterElement, JsonToken::RightBracket { .. }) => return Ok(Some(self.close())),
+        (FirstKey, JsonToken::RightBrace { .. }) | (AfterEntry, JsonToken::RightBrace { .. }) => {
+          return Ok(Some(self.close()))
+        },
+        (FirstKey | Key, JsonToken::String { val, .. } | JsonToken::Identifier { val, .. }) => {
+          self.state = Colon;
+          return Ok(Some(JsonEvent::Key(val)));
+        },
+        (Colon, JsonToken::Colon { .. }) => self.state = Value,
+        (AfterElement, JsonToken::Comma { .. }) => {
+          self.state = match self.lexer.options.trailing_commas {
+            true => FirstElement,
+            false => Value,
+          }
+        },
+        (AfterEntry, JsonToken::Comma { .. }) => {
+          self.state = match self.lexer.options.trailing_commas {
+            true => FirstKey,
+            false => Key,
+          }
+        },
+        (End | Done, JsonToken::Eof { .. }) => {
+          self.state = Done;
+          return Ok(None);
+        },
+        (_, JsonToken::Eof { pos }) => {
+          return Err(
+            Error::json("Unexpected end of input".to_string(), pos)
+              .with_kind(ErrorKind::UnexpectedEof),
+          )
+        },
+        (Value | FirstElement, _) => {
+          return Err(
+            Error::json("Expected a value".to_string(), pos).with_kind(ErrorKind::UnexpectedToken),
+          )
+        },
+        (FirstKey | Key, _) => {
+          return Err(
+            Error::json("Expected string".to_string(), pos).with_kind(ErrorKind::UnexpectedToken),
+          )
+        },
+        (Colon, _) => {
+          return Err(
+            Error::json("Expected ':'".to_string(), pos).with_kind(ErrorKind::UnexpectedToken),
+          )
+        },
+        (AfterElement, _) => {
+          return Err(
+            Error::json("Expected ',' or ']'".to_string(), pos)
+              .with_kind(ErrorKind::UnexpectedToken),
+          )
+        },
+        (AfterEntry, _) => {
+          return Err(
+            Error::json("Expected ',' or '}'".to_string(), pos)
+              .with_kind(ErrorKind::UnexpectedToken),
+          )
+        },
+        (End | Done, _) => {
+          return Err(
+            Error::json("Unexpected trailing content".to_string(), pos)
+              .with_kind(ErrorKind::TrailingContent),
+          )
+        },
+      }
+    }
+  }
+}
+
+impl Iterator for JsonEvents<'_> {
+  type Item = Result<JsonEvent, Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.state == EventState::Done {
+      return None;
+    }
+    match self.next_event() {
+      Ok(event) => event.map(Ok),
+      Err(error) => {
+        self.state = EventState::Done;
+        Some(Err(error.locate(self.lexer.json)))
+      },
+    }
+  }
+}
+
+/// Iterator over the values of a newline-delimited JSON (NDJSON or JSON
+/// Lines) document, one per non-blank line. Created with [`JSON::lines`].
+///
+/// Each line is parsed on its own, so a malformed line yields an Error and
+/// the iterator moves on to the next one. Error lines and columns refer to the
+/// whole document.
+pub struct JsonLines<'a> {
+  json:    &'a str,
+  options: ParseOptions,
+  offset:  usize,
+}
+
+impl Iterator for JsonLines<'_> {
+  type Item = Result<JsonValue, Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    while self.offset < self.json.len() {
+      let start = self.offset;
+      let rest = &self.json[start..];
+      let line = match rest.find('\n') {
+        Some(end) => {
+          self.offset += end + 1;
+          &rest[..end]
+        },
+        None => {
+          self.offset = self.json.len();
+          rest
+        },
+      };
+      if line.trim().is_empty() {
+        continue;
+      }
+      let mut parser = JsonParser::new(line, self.options);
+      parser.at_start = start == 0;
+      return Some(parser.parse_document().map_err(|mut error| {
+        error.index += start;
+        error.locate(self.json)
+      }));
+    }
+    None
+  }
+}
+
+/// Writes `val` as JSON into `out`, piece by piece, so the output never has
+/// to be held in memory as a whole.
+fn write_json<W: fmt::Write>(
+  val: &JsonValue,
+  options: &StringifyOptions,
+  out: &mut W,
+) -> fmt::Result {
+  write_json_tree(val, options, false, out)
+}
+
+/// Writes `val` with an explicit stack of the arrays and objects it is in
+/// instead of recursing, so values nested arbitrarily deep can be written.
+/// `canonical` writes scalars and orders keys as [`write_canonical_json`]
+/// does.
+fn write_json_tree<W: fmt::Write>(
+  val: &JsonValue,
+  options: &StringifyOptions,
+  canonical: bool,
+  out: &mut W,
+) -> fmt::Result {
+  /// An array or object being written, with its entries still to come.
+  struct Frame<'a> {
+    entries: std::vec::IntoIter<(Option<&'a String>, &'a JsonValue)>,
+    started: bool,
+    close:   char,
+  }
+  let colon_space = options
+    .colon_space
+    .unwrap_or(options.pretty != PrettyMode::Compact);
+  let mut stack: Vec<Frame> = vec![];
+  let mut value = val;
+  loop {
+    match value {
+      JsonValue::Array(arr) if !arr.is_empty() => {
+        out.write_char('[')?;
+        stack.push(Frame {
+          entries: arr
+            .iter()
+            .map(|v| (None, v))
+            .collect::<Vec<_>>()
+            .into_iter(),
+          started: false,
+          close:   ']',
+        });
+      },
+      JsonValue::Object(obj) if !obj.is_empty() => {
+        let mut entries: Vec<_> = obj.iter().map(|(k, v)| (Some(k), v)).collect();
+        if canonical {
+          entries
+            .sort_by(|(a, _), (b, _)| a.unwrap().encode_utf16().cmp(b.unwrap().encode_utf16()));
+        } else if options.sort_keys {
+          entries.sort_by_key(|(k, _)| *k);
+        }
+        out.write_char('{')?;
+        stack.push(Frame {
+          entries: entries.into_iter(),
+          started: false,
+          close:   '}',
+        });
+      },
+      val if canonical => write_canonical_scalar(val, out)?,
+      val => write_json_scalar(val, options, out)?,
+    }
+    // Move on to the next entry, closing the containers that are done.
+    loop {
+      let level = stack.len();
+      let Some(frame) = stack.last_mut() else {
+        return Ok(());
+      };
+      match frame.entries.next() {
+        Some((key, next)) => {
+          if frame.started {
+            out.write_char(',')?;
+          }
+          write_json_separator(options, level, frame.started, out)?;
+          frame.started = true;
+          if let Some(key) = key {
+            write_json_string(key, options, out)?;
+            out.write_char(':')?;
+            if colon_space {
+              out.write_char(' ')?;
+            }
+          }
+          value = next;
+          break;
+        },
+        None => {
+          let close = frame.close;
+          stack.pop();
+          write_json_separator(options, level - 1, false, out)?;
+          out.write_char(close)?;
+        },
+      }
+    }
+  }
+}
+
+/// Writes a value that is not a nonempty array or object.
+fn write_json_scalar<W: fmt::Write>(
+  val: &JsonValue,
+  options: &StringifyOptions,
+  out: &mut W,
+) -> fmt::Result {
+  match val {
+    JsonValue::Null => out.write_str("null"),
+    JsonValue::String(s) => write_json_string(s, options, out),
+    JsonValue::Number(n) if n.is_finite() && options.serde_floats => write_serde_number(*n, out),
+    JsonValue::Number(n) if n.is_finite() => write_json_number(*n, out),
+    JsonValue::Number(_) if !options.allow_nan => out.write_str("null"),
+    JsonValue::Number(n) if n.is_nan() => out.write_str("NaN"),
+    JsonValue::Number(n) if *n > 0.0 => out.write_str("Infinity"),
+    JsonValue::Number(_) => out.write_str("-Infinity"),
+    JsonValue::Integer(i) => write!(out, "{}", i),
+    JsonValue::RawNumber(raw) => out.write_str(raw),
+    JsonValue::Boolean(b) => write!(out, "{}", b),
+    JsonValue::Array(_) => out.write_str("[]"),
+    JsonValue::Object(_) => out.write_str("{}"),
+  }
+}
+
+/// Writes a finite number in its shortest form that reads back as the same
+/// `f64`. Like JavaScript, plain decimal notation is used for magnitudes from
+/// `1e-6` up to `1e21`, and exponent notation such as `1e+21` outside that.
+fn write_json_number<W: fmt::Write>(n: f64, out: &mut W) -> fmt::Result {
+  let scientific = format!("{:e}", n);
+  let (mantissa, exponent) = scientific.split_once('e').unwrap();
+  match exponent.parse::<i32>().unwrap() {
+    -6..=20 => write!(out, "{}", n),
+    exponent if exponent > 0 => write!(out, "{}e+{}", mantissa, exponent),
+    exponent => write!(out, "{}e{}", mantissa, exponent),
+  }
+}
+
+/// Writes a finite number in its shortest form like serde_json does: plain
+/// decimal notation, with `.0` if the number is whole, for magnitudes from
+/// `1e-5` up to `1e16`, and exponent notation such as `1e16` or `1.5e-7`
+/// outside that.
+fn write_serde_number<W: fmt::Write>(n: f64, out: &mut W) -> fmt::Result {
+  let scientific = format!("{:e}", n);
+  let (_, exponent) = scientific.split_once('e').unwrap();
+  match exponent.parse::<i32>().unwrap() {
+    -5..=15 if n.fract() == 0.0 => write!(out, "{:.1}", n),
+    -5..=15 => write!(out, "{}", n),
+    _ => out.write_str(&scientific),
+  }
+}
+
+/// Writes `val` in the canonical form of RFC 8785 (JCS): no whitespace, keys
+/// sorted by their UTF-16 code units, numbers as IEEE doubles in their
+/// shortest JavaScript form and strings with only the mandatory escapes.
+fn write_canonical_json<W: fmt::Write>(val: &JsonValue, out: &mut W) -> fmt::Result {
+  write_json_tree(val, &StringifyOptions::default(), true, out)
+}
+
+/// Writes a value that is not a nonempty array or object in the canonical
+/// form of [`write_canonical_json`].
+fn write_canonical_scalar<W: fmt::Write>(val: &JsonValue, out: &mut W) -> fmt::Result {
+  match val {
+    JsonValue::Number(n) if *n == 0.0 => out.write_char('0'),
+    JsonValue::Number(n) if n.is_finite() => write_json_number(*n, out),
+    JsonValue::Integer(i) => write_canonical_scalar(&JsonValue::Number(*i as f64), out),
+    JsonValue::RawNumber(raw) => {
+      write_canonical_scalar(&JsonValue::Number(raw.parse().unwrap_or(f64::NAN)), out)
+    },
+    val => write_json_scalar(val, &StringifyOptions::default(), out),
+  }
+}
+
+/// Writes the whitespace that goes before an entry at `level`, or before the
+/// closing bracket of a container at `level`. `after_comma` tells whether a
+/// comma precedes it, as only those are followed by a space in
+/// [`PrettyMode::Spaces`] output.
+fn write_json_separator<W: fmt::Write>(
+  options: &StringifyOptions,
+  level: usize,
+  after_comma: bool,
+  out: &mut W,
+) -> fmt::Result {
+  match options.pretty {
+    PrettyMode::Compact => Ok(()),
+    PrettyMode::Spaces if after_comma => out.write_char(' '),
+    PrettyMode::Spaces => Ok(()),
+    PrettyMode::Indented => {
+      out.write_char('\n')?;
+      if options.indent.is_empty() {
+        return Ok(());
+      }
+      for _ in 0..level {
+        out.write_str(&options.indent)?;
+      }
+      Ok(())
+    },
+  }
+}
+
+/// Writes `s` as a quoted JSON string in a single pass. Runs of characters
+/// that need no escaping are copied straight from `s` as one slice.
+fn write_json_string<W: fmt::Write>(
+  s: &str,
+  options: &StringifyOptions,
+  out: &mut W,
+) -> fmt::Result {
+  out.write_char('"')?;
+  let mut plain = 0;
+  for (i, c) in s.char_indices() {
+    let escape = match c {
+      '\\' => "\\\\",
+      '/' if options.escape_slash => "\\/",
+      '"' => "\\\"",
+      '\x08' => "\\b",
+      '\x0C' => "\\f",
+      '\n' => "\\n",
+      '\r' => "\\r",
+      '\t' => "\\t",
+      c if c < '\x20' || options.escape_non_ascii && !c.is_ascii() => "",
+      _ => continue,
+    };
+    out.write_str(&s[plain..i])?;
+    match escape {
+      "" => {
+        for unit in c.encode_utf16(&mut [0; 2]) {
+          write!(out, "\\u{:04x}", unit)?;
+        }
+      },
+      escape => out.write_str(escape)?,
+    }
+    plain = i + c.len_utf8();
+  }
+  out.write_str(&s[plain..])?;
+  out.write_char('"')
+}
+
+/// How much whitespace [`JSON::stringify`] puts into its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PrettyMode {
+  /// No whitespace at all.
+  #[default]
+  Compact,
+  /// Everything on one line, with a space after every comma and colon, as in
+  /// `[1, {"a": null}]`.
+  Spaces,
+  /// One entry per line, indented by nesting level.
+  Indented,
+}
+
+/// Formatting options for [`JSON::stringify_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringifyOptions {
+  /// The kind of pretty formatting to use.
+  pub pretty:           PrettyMode,
+  /// The unit repeated once per nesting level in [`PrettyMode::Indented`]
+  /// output, two spaces by default. An empty string puts every entry on its
+  /// own line without indentation.
+  pub indent:           String,
+  /// Whether to emit object keys in sorted order instead of insertion order.
+  pub sort_keys:        bool,
+  /// Whether to write non-finite numbers as `NaN`, `Infinity` and
+  /// `-Infinity`. Otherwise they are written as `null`, which keeps the output
+  /// valid JSON.
+  pub allow_nan:        bool,
+  /// Whether to write `/` in strings as `\/`. Both are valid JSON, but some
+  /// consumers embed the output in HTML `<script>` tags and want `</` broken
+  /// up.
+  pub escape_slash:     bool,
+  /// Whether to put a space after the `:` between a key and its value. `None`
+  /// follows `pretty`: no space in [`PrettyMode::Compact`] output, one space
+  /// otherwise.
+  pub colon_space:      Option<bool>,
+  /// Whether to write every non-ASCII character as a `\uXXXX` escape, using
+  /// a surrogate pair outside the Basic Multilingual Plane, so the output is
+  /// pure ASCII.
+  pub escape_non_ascii: bool,
+  /// Whether to end the output with a `\n`, as files usually do.
+  pub trailing_newline: bool,
+  /// Whether to write [`JsonValue::Number`] values like serde_json: a whole
+  /// float keeps its `.0`, and exponent notation, such as `1e16` or
+  /// `1.5e-7`, is used from `1e16` up and below `1e-5`. Otherwise numbers
+  /// are written as in JavaScript.
+  pub serde_floats:     bool,
+}
+
+impl StringifyOptions {
+  /// The default options: compact output in insertion order.
+  pub fn new() -> Self { Self::default() }
+
+  /// Options that lay output out exactly like `serde_json::to_string_pretty`:
+  /// two-space indentation, one entry per line, a space after each `:`,
+  /// `[]` and `{}` for empty containers and no trailing newline. Keys keep
+  /// their insertion order, as with serde_json's `preserve_order` feature;
+  /// add `.sort_keys(true)` to match its default map. Floats are written as
+  /// serde_json writes them, e.g. `1.0` and `1e21`.
+  pub fn serde_pretty() -> Self {
+    Self {
+      pretty: PrettyMode::Indented,
+      indent: "  ".to_string(),
+      colon_space: Some(true),
+      serde_floats: true,
+      ..Default::default()
+    }
+  }
+
+  /// Sets the kind of pretty formatting to use.
+  pub fn pretty(mut self, pretty: PrettyMode) -> Self {
+    self.pretty = pretty;
+    self
+  }
+
+  /// Sets the unit of indentation for [`PrettyMode::Indented`] output.
+  pub fn indent(mut self, indent: impl Into<String>) -> Self {
+    self.indent = indent.into();
+    self
+  }
+
+  /// Sets whether object keys are emitted in sorted order.
+  pub fn sort_keys(mut self, sort_keys: bool) -> Self {
+    self.sort_keys = sort_keys;
+    self
+  }
+
+  /// Sets whether non-finite numbers are written as `NaN` and `Infinity`.
+  pub fn allow_nan(mut self, allow_nan: bool) -> Self {
+    self.allow_nan = allow_nan;
+    self
+  }
+
+  /// Sets whether `/` in strings is written as `\/`.
+  pub fn escape_slash(mut self, escape_slash: bool) -> Self {
+    self.escape_slash = escape_slash;
+    self
+  }
+
+  /// Sets whether a space follows the `:` after object keys, regardless of
+  /// the pretty mode.
+  pub fn colon_space(mut self, colon_space: bool) -> Self {
+    self.colon_space = Some(colon_space);
+    self
+  }
+
+  /// Sets whether non-ASCII characters are written as `\uXXXX` escapes.
+  pub fn escape_non_ascii(mut self, escape_non_ascii: bool) -> Self {
+    self.escape_non_ascii = escape_non_ascii;
+    self
+  }
+
+  /// Sets whether the output ends with a `\n`.
+  pub fn trailing_newline(mut self, trailing_newline: bool) -> Self {
+    self.trailing_newline = trailing_newline;
+    self
+  }
+
+  /// Sets whether floats are written like serde_json writes them.
+  pub fn serde_floats(mut self, serde_floats: bool) -> Self {
+    self.serde_floats = serde_floats;
+    self
+  }
+}
+
+impl Default for StringifyOptions {
+  fn default() -> Self {
+    Self {
+      pretty:           PrettyMode::Compact,
+      indent:           "  ".to_string(),
+      sort_keys:        false,
+      allow_nan:        false,
+      escape_slash:     false,
+      colon_space:      None,
+      escape_non_ascii: false,
+      trailing_newline: false,
+      serde_floats:     false,
+    }
+  }
+}
+
+/// What [`JSON::parse_with`] does when an object has the same key twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyPolicy {
+  /// Fail with a "Duplicate key" error.
+  #[default]
+  Error,
+  /// Keep the first value and ignore later ones.
+  First,
+  /// Keep the last value, like JavaScript's `JSON.parse`. The key stays at the
+  /// position where it first appeared.
+  Last,
+}
+
+/// How [`JSON::parse_with`] turns number literals into values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberMode {
+  /// Integers that fit into an `i64` become [`JsonValue::Integer`], all
+  /// other numbers [`JsonValue::Number`].
+  #[default]
+  Native,
+  /// Every number becomes a [`JsonValue::RawNumber`] holding its literal, so
+  /// no precision is lost. `NaN`, `Infinity`, hex numbers and literals only
+  /// accepted by `lenient_numbers` are still parsed natively.
+  Raw,
+}
+
+/// The default [`ParseOptions::max_depth`], which the parsers for other
+/// formats use as their fixed nesting limit.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Options for [`JSON::parse_with`]. The default is strict RFC 8259 parsing,
+/// which is what [`JSON::parse`] uses; each relaxation is switched on by its
+/// own field, so new ones can be added without breaking callers that start
+/// from the default.
+///
+/// ```
+/// use parsers::json::{ParseOptions, JSON};
+///
+/// let options = ParseOptions {
+///   comments: true,
+///   trailing_commas: true,
+///   ..Default::default()
+/// };
+/// let value = JSON::parse_with("[1, 2, /* three */]", options).unwrap();
+/// assert_eq!(value.len(), 2);
+/// assert!(JSON::parse("[1, 2, /* three */]").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+  /// How many arrays and objects may be nested inside each other before
+  /// parsing fails. Neither parsing nor dropping, cloning, comparing,
+  /// hashing or stringifying the resulting value recurses, so this can be
+  /// raised freely as far as those go. Other methods that walk a whole value,
+  /// such as `{:?}`, [`JsonValue::flatten`] and [`JsonValue::sorted`], do
+  /// recurse once per level.
+  pub max_depth:          usize,
+  /// The longest a quoted string or key may be, in bytes after unescaping, or
+  /// `None` for no limit.
+  pub max_string_len:     Option<usize>,
+  /// How many values, counting every array, object, element and member
+  /// value, a document may create, or `None` for no limit. Not applied by
+  /// [`JSON::events`], which builds no values.
+  pub max_elements:       Option<usize>,
+  /// Whether to accept `NaN`, `Infinity` and `-Infinity` as numbers, as
+  /// emitted by Python's `json` module and many JavaScript serializers.
+  pub allow_nan:          bool,
+  /// Whether to skip `// line` and `/* block */` comments like whitespace, as
+  /// in JSONC files such as VS Code settings.
+  pub comments:           bool,
+  /// Whether to accept a single trailing comma before the closing `]` or `}`
+  /// of an array or object.
+  pub trailing_commas:    bool,
+  /// Whether object keys may be written without quotes when they are
+  /// identifiers, as in `{name: "x"}`. Keywords such as `null` and `true`
+  /// count as identifiers here.
+  pub unquoted_keys:      bool,
+  /// Whether strings may be delimited by `'` as well as `"`. Inside them `\'`
+  /// escapes a single quote.
+  pub single_quotes:      bool,
+  /// Whether strings may contain raw control characters (below U+0020), such
+  /// as a literal tab or line break, instead of escapes only.
+  pub control_characters: bool,
+  /// Whether to accept hexadecimal integers such as `0xFF` and `-0x10`.
+  pub hex_numbers:        bool,
+  /// Whether to accept numbers with a leading `+` or with nothing before or
+  /// after the decimal point, such as `+1`, `.5` and `5.`.
+  pub lenient_numbers:    bool,
+  /// Whether numbers are parsed into `f64`/`i64` or kept as their literals.
+  pub number_mode:        NumberMode,
+  /// What to do with repeated keys in an object. Not applied by
+  /// [`JSON::events`], which never remembers keys.
+  pub duplicate_keys:     DuplicateKeyPolicy,
+}
+
+impl Default for ParseOptions {
+  fn default() -> Self {
+    Self {
+      max_depth:          DEFAULT_MAX_DEPTH,
+      max_string_len:     None,
+      max_elements:       None,
+      allow_nan:          false,
+      comments:           false,
+      trailing_commas:    false,
+      unquoted_keys:      false,
+      single_quotes:      false,
+      control_characters: false,
+      hex_numbers:        false,
+      lenient_numbers:    false,
+      number_mode:        NumberMode::Native,
+      duplicate_keys:     DuplicateKeyPolicy::Error,
+    }
+  }
+}
+
+impl ParseOptions {
+  /// Options accepting [JSON5](https://json5.org) documents: every relaxation
+  /// is switched on. Individual fields can still be turned off afterwards.
+  pub fn json5() -> Self {
+    Self {
+      allow_nan: true,
+      comments: true,
+      trailing_commas: true,
+      unquoted_keys: true,
+      single_quotes: true,
+      hex_numbers: true,
+      lenient_numbers: true,
+      ..Default::default()
+    }
+  }
+}
+
+/// Struct with methods for parsing and stringifying JSON similar to the
+/// JavaScript JSON object.
+pub struct JSON {}
+
+impl JSON {
+  /// Parses a JSON string and returns a JsonValue struct. A UTF-8 byte order
+  /// mark at the very start of the string is skipped, here and in the other
+  /// parsing functions.
+  ///
+  /// # Arguments
+  ///
+  /// - `json` - The JSON string to parse. Anything that borrows as a `str` is
+  ///   accepted, so both `&str` and `String` work without extra allocation.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if the JSON string is invalid.
+  pub fn parse(json: impl AsRef<str>) -> Result<JsonValue, Error> {
+    JSON::parse_with(json, ParseOptions::default())
+  }
+
+  /// Parses a JSON string like [`JSON::parse`], but with custom options.
+  ///
+  /// # Arguments
+  ///
+  /// - `json` - The JSON string to parse.
+  /// - `options` - The options controlling what input is accepted.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if the JSON string is invalid under `options`.
+  pub fn parse_with(json: impl AsRef<str>, options: ParseOptions) -> Result<JsonValue, Error> {
+    let mut parser = JsonParser::new(json.as_ref(), options);
+    parser.parse()
+  }
+
+  /// Parses JSON from raw bytes, such as the contents of a file or a network
+  /// buffer. The bytes are validated as UTF-8 and then lexed in place, without
+  /// copying them into a `String`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error of kind [`ErrorKind::Encoding`] at the offset of the
+  /// first invalid byte if `bytes` is not UTF-8, or a syntax Error if the JSON
+  /// is invalid.
+  pub fn parse_bytes(bytes: &[u8]) -> Result<JsonValue, Error> {
+    match std::str::from_utf8(bytes) {
+      Ok(json) => JSON::parse(json),
+      Err(error) => Err(
+        Error::json("Invalid UTF-8".to_string(), error.valid_up_to())
+          .with_kind(ErrorKind::Encoding),
+      ),
+    }
+  }
+
+  /// Reads `reader` to the end and parses its contents as JSON, for parsing
+  /// straight from a file or socket.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error of kind [`ErrorKind::Io`] if reading fails, one of kind
+  /// [`ErrorKind::Encoding`] if the contents are not UTF-8, and a syntax Error
+  /// if they are not valid JSON.
+  pub fn parse_reader<R: Read>(mut reader: R) -> Result<JsonValue, Error> {
+    let mut bytes = Vec::new();
+    if let Err(error) = reader.read_to_end(&mut bytes) {
+      return Err(Error::io(FileType::JSON, error));
+    }
+    JSON::parse_bytes(&bytes)
+  }
+
+  /// Parses a JSON5 string, which may use comments, trailing commas, unquoted
+  /// keys, single-quoted strings, hex numbers, lenient decimal points, `NaN`
+  /// and `Infinity`. Shorthand for [`JSON::parse_with`] and
+  /// [`ParseOptions::json5`].
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if the JSON5 string is invalid.
+  pub fn parse_json5(json: impl AsRef<str>) -> Result<JsonValue, Error> {
+    JSON::parse_with(json, ParseOptions::json5())
+  }
+
+  /// Parses the JSON value at the start of `json` and ignores whatever comes
+  /// after it, for values embedded in a larger stream.
+  ///
+  /// # Returns
+  ///
+  /// The value and the byte offset just past its last character, where the
+  /// rest of the stream begins.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if the value at the start of `json` is invalid.
+  pub fn parse_partial(json: &str) -> Result<(JsonValue, usize), Error> {
+    JsonParser::new(json, ParseOptions::default()).parse_partial()
+  }
+
+  /// Parses a stream of JSON values written back to back, such as
+  /// `{"a":1}{"b":2}` or `1 2 3`, and returns them in order. Whitespace
+  /// between values is optional unless it is needed to tell them apart.
+  ///
+  /// # Errors
+  ///
+  /// Returns the Error of the first invalid value, positioned within the
+  /// whole of `json`.
+  pub fn parse_many(json: &str) -> Result<Vec<JsonValue>, Error> {
+    let mut values = vec![];
+    let mut offset = 0;
+    loop {
+      let rest = &json[offset..];
+      let skipped = rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+      offset += skipped;
+      if offset == json.len() {
+        return Ok(values);
+      }
+      let mut parser = JsonParser::new(&json[offset..], ParseOptions::default());
+      parser.at_start = offset == 0;
+      let (value, end) = parser.parse_prefix().map_err(|mut error| {
+        error.index += offset;
+        error.locate(json)
+      })?;
+      values.push(value);
+      offset += end;
+    }
+  }
+
+  /// Parses a JSON string like [`JSON::parse`], but keeps going after syntax
+  /// errors so that all of them can be reported at once, as an editor or
+  /// linter would want. After an error the parser skips ahead to the next
+  /// `,`, `]` or `}` and carries on; the broken element is left out of the
+  /// result.
+  ///
+  /// # Arguments
+  ///
+  /// - `json` - The JSON string to parse.
+  ///
+  /// # Returns
+  ///
+  /// The parsed value, or `None` if the input could not be recovered from
+  /// (for example because it ends in the middle of a container), and every
+  /// Error found, in source order. The Error list is empty for valid JSON.
+  pub fn parse_collect(json: &str) -> (Option<JsonValue>, Vec<Error>) {
+    JsonParser::new(json, ParseOptions::default()).parse_collect()
+  }
+
+  /// Checks that a JSON string is well formed without building a
+  /// [`JsonValue`] tree. It walks the document with [`JSON::events`], so
+  /// memory use only grows with nesting depth. Unlike [`JSON::parse`] it does
+  /// not look for duplicate object keys.
+  ///
+  /// # Errors
+  ///
+  /// Returns the first Error found if the JSON string is invalid.
+  pub fn validate(json: &str) -> Result<(), Error> {
+    JSON::events(json).try_for_each(|event| event.map(drop))
+  }
+
+  /// Parses a newline-delimited JSON document, where every non-blank line
+  /// holds one complete value, and returns the values in order.
+  ///
+  /// # Arguments
+  ///
+  /// - `json` - The NDJSON string to parse.
+  ///
+  /// # Errors
+  ///
+  /// Returns the Error of the first line that is invalid. Its `line` is the
+  /// line number within `json`.
+  pub fn parse_lines(json: &str) -> Result<Vec<JsonValue>, Error> { JSON::lines(json).collect() }
+
+  /// Returns an iterator over the values of a newline-delimited JSON
+  /// document, yielding one result per non-blank line. Unlike
+  /// [`JSON::parse_lines`] it keeps going after a malformed line.
+  pub fn lines(json: &str) -> JsonLines<'_> { JSON::lines_with(json, ParseOptions::default()) }
+
+  /// Returns an iterator over NDJSON values like [`JSON::lines`], but with
+  /// custom options for parsing each line.
+  pub fn lines_with(json: &str, options: ParseOptions) -> JsonLines<'_> {
+    JsonLines {
+      json,
+      options,
+      offset: 0,
+    }
+  }
+
+  /// Returns an iterator of [`JsonEvent`]s over a JSON string, for walking
+  /// large documents without building the whole [`JsonValue`] tree.
+  ///
+  /// # Arguments
+  ///
+  /// - `json` - The JSON string to walk.
+  ///
+  /// # Errors
+  ///
+  /// The iterator yields an Error, and then stops, if the JSON string is
+  /// invalid.
+  pub fn events(json: &str) -> JsonEvents<'_> { JSON::events_with(json, ParseOptions::default()) }
+
+  /// Returns an iterator of [`JsonEvent`]s like [`JSON::events`], but with
+  /// custom options.
+  pub fn events_with(json: &str, options: ParseOptions) -> JsonEvents<'_> {
+    JsonEvents::new(json, options)
+  }
+
+  /// Stringifies a JsonValue struct and returns a JSON string.
+  ///
+  /// # Arguments
+  ///
+  /// - `value` - The JsonValue struct to stringify.
+  /// - `pretty` - The kind of pretty formatting to use.
+  ///
+  /// # Errors
+  ///
+  /// Never returns an Error.
+  pub fn stringify(value: &JsonValue, pretty: PrettyMode) -> String {
+    JSON::stringify_with(value, &StringifyOptions {
+      pretty,
+      ..Default::default()
+    })
+  }
+
+  /// Stringifies a JsonValue struct with one entry per line, indented by two
+  /// spaces per level. Same as `JSON::stringify(value, PrettyMode::Indented)`.
+  pub fn pretty(value: &JsonValue) -> String { JSON::stringify(value, PrettyMode::Indented) }
+
+  /// Stringifies a JsonValue struct without any whitespace. Same as
+  /// `JSON::stringify(value, PrettyMode::Compact)`.
+  pub fn compact(value: &JsonValue) -> String { JSON::stringify(value, PrettyMode::Compact) }
+
+  /// Stringifies a JsonValue struct with full control over the output format.
+  ///
+  /// # Arguments
+  ///
+  /// - `value` - The JsonValue struct to stringify.
+  /// - `options` - The formatting options to use.
+  pub fn stringify_with(value: &JsonValue, options: &StringifyOptions) -> String {
+    let mut result = String::new();
+    write_json(value, options, &mut result).unwrap();
+    if options.trailing_newline {
+      result.push('\n');
+    }
+    result
+  }
+
+  /// Stringifies a JsonValue struct into `writer` as it goes, instead of
+  /// building the whole output as a `String` first.
+  ///
+  /// # Arguments
+  ///
+  /// - `value` - The JsonValue struct to stringify.
+  /// - `writer` - Where to write the JSON string.
+  /// - `pretty` - The kind of pretty formatting to use.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if `writer` does.
+  pub fn stringify_to<W: fmt::Write>(
+    value: &JsonValue,
+    writer: &mut W,
+    pretty: PrettyMode,
+  ) -> fmt::Result {
+    let options = StringifyOptions {
+      pretty,
+      ..Default::default()
+    };
+    write_json(value, &options, writer)
+  }
+
+  /// Stringifies a JsonValue struct in the canonical form of the JSON
+  /// Canonicalization Scheme (RFC 8785), so equal values always produce the
+  /// same bytes for hashing and signing.
+  ///
+  /// Integers are written as the IEEE double they round to, as the RFC
+  /// requires. Non-finite numbers, which the RFC rejects, are written as
+  /// `null` like in [`JSON::stringify`].
+  ///
+  /// # Arguments
+  ///
+  /// - `value` - The JsonValue struct to canonicalize.
+  pub fn canonicalize(value: &JsonValue) -> String {
+    let mut result = String::new();
+    write_canonical_json(value, &mut result).unwrap();
+    result
+  }
+
+  /// Stringifies a JsonValue struct using the old numeric pretty levels: 0 for
+  /// no whitespace, 1 for spaces, 2 for newlines. Any other level behaves like
+  /// 0.
+  #[deprecated(note = "use `JSON::stringify` with a `PrettyMode` instead")]
+  pub fn stringify_level(value: &JsonValue, pretty: i32) -> String {
+    let pretty = match pretty {
+      1 => PrettyMode::Spaces,
+      2 => PrettyMode::Indented,
+      _ => PrettyMode::Compact,
+    };
+    JSON::stringify(value, pretty)
+  }
+
+  /// Stringifies a JsonValue struct like [`JSON::stringify`], but emits the
+  /// keys of every object in sorted order so the output is deterministic.
+  ///
+  /// # Arguments
+  ///
+  /// - `value` - The JsonValue struct to stringify.
+  /// - `pretty` - The kind of pretty formatting to use.
+  pub fn stringify_sorted(value: &JsonValue, pretty: PrettyMode) -> String {
+    JSON::stringify_with(value, &StringifyOptions {
+      pretty,
+      sort_keys: true,
+      ..Default::default()
+    })
+  }
 }
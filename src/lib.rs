@@ -1,6 +1,123 @@
 mod error;
-use error::Error;
+pub use error::{Error, ErrorKind, FileType};
+pub mod csv;
+pub mod ini;
 pub mod json;
+pub mod percent;
+pub mod querystring;
+pub mod toml;
+pub mod xml;
+pub mod yaml;
+
+use json::JsonValue;
+
+/// Parses `input` as `filetype` into a [`JsonValue`], using the format's own
+/// parser: [`json::JSON::parse`], [`csv::CSV::parse_records`],
+/// [`toml::TOML::parse`], [`ini::INI::parse`],
+/// [`querystring::QueryString::parse`], [`xml::XML::parse`] or
+/// [`yaml::YAML::parse`]. Percent-encoded text decodes to a
+/// [`JsonValue::String`].
+///
+/// # Arguments
+///
+/// - `input` - The string to parse.
+/// - `filetype` - The format `input` is in.
+///
+/// # Errors
+///
+/// Returns the Error of the format's parser if `input` is invalid.
+pub fn parse(input: &str, filetype: FileType) -> Result<JsonValue, Error> {
+  match filetype {
+    FileType::JSON => json::JSON::parse(input),
+    FileType::CSV => csv::CSV::parse_records(input),
+    FileType::TOML => toml::TOML::parse(input),
+    FileType::INI => ini::INI::parse(input),
+    FileType::QueryString => querystring::QueryString::parse(input),
+    FileType::PercentEncoding => percent::decode(input).map(JsonValue::String),
+    FileType::XML => xml::XML::parse(input),
+    FileType::YAML => yaml::YAML::parse(input),
+  }
+}
+
+/// Formats tried by [`parse_auto`] when the content suggests none, in this
+/// order. YAML reads most text as a plain scalar, so the formats after it
+/// are only reached for input it rejects.
+const AUTO_ORDER: [FileType; 6] = [
+  FileType::JSON,
+  FileType::TOML,
+  FileType::INI,
+  FileType::XML,
+  FileType::YAML,
+  FileType::CSV,
+];
+
+/// Lists the formats `input` looks like, most likely first.
+fn sniff(input: &str) -> Vec<FileType> {
+  let mut lines = input
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty() && !line.starts_with(['#', ';']));
+  let Some(first) = lines.clone().next() else {
+    return vec![];
+  };
+  let commas = |line: &str| line.matches(',').count();
+  match first.chars().next().unwrap() {
+    '{' => vec![FileType::JSON],
+    '[' => vec![FileType::JSON, FileType::TOML, FileType::INI],
+    '<' => vec![FileType::XML],
+    _ if commas(first) > 0
+      && lines
+        .by_ref()
+        .take(5)
+        .all(|line| commas(line) == commas(first)) =>
+    {
+      vec![FileType::CSV]
+    },
+    _ if first
+      .split_once('=')
+      .is_some_and(|(key, _)| !key.is_empty() && !key.contains(':')) =>
+    {
+      vec![FileType::TOML, FileType::INI]
+    },
+    _ if first.starts_with("- ") || first.contains(": ") || first.ends_with(':') => {
+      vec![FileType::YAML]
+    },
+    _ => vec![],
+  }
+}
+
+/// Guesses the format of `input` from its content and parses it like
+/// [`parse`]. A leading `{` or `[` suggests JSON (or a TOML/INI section
+/// header), a leading `<` XML, lines with the same number of commas CSV,
+/// `key = value` TOML and then INI, and `key: value` or `- item` YAML. The
+/// suggested formats are tried in that order; if there are none, all
+/// formats are tried in the order JSON, TOML, INI, XML, YAML, CSV. The
+/// first that parses wins.
+///
+/// # Arguments
+///
+/// - `input` - The string to parse.
+///
+/// # Errors
+///
+/// Returns the Error of the most likely format if no format can parse
+/// `input`.
+pub fn parse_auto(input: &str) -> Result<(FileType, JsonValue), Error> {
+  let mut candidates = sniff(input);
+  if candidates.is_empty() {
+    candidates = AUTO_ORDER.to_vec();
+  }
+  let mut first_error = None;
+  for filetype in candidates {
+    match parse(input, filetype) {
+      Ok(value) => return Ok((filetype, value)),
+      Err(error) => {
+        first_error.get_or_insert(error);
+      },
+    }
+  }
+  Err(first_error.unwrap())
+}
 
 #[cfg(test)]
 mod json_tests {
@@ -12,37 +129,800 @@ mod json_tests {
 
   #[test]
   fn json_parse_null() {
-    let result = JSON::parse("null".to_string()).unwrap();
+    let result = JSON::parse("null").unwrap();
     assert_eq!(result, JsonValue::Null);
   }
 
   #[test]
   fn json_parse_boolean() {
-    let result = JSON::parse("true".to_string()).unwrap();
+    let result = JSON::parse("true").unwrap();
     assert_eq!(result, JsonValue::Boolean(true));
-    let result = JSON::parse("false".to_string()).unwrap();
+    let result = JSON::parse("false").unwrap();
     assert_eq!(result, JsonValue::Boolean(false));
   }
 
   #[test]
   fn json_parse_number() {
-    let result = JSON::parse("123".to_string()).unwrap();
+    let result = JSON::parse("123").unwrap();
     assert_eq!(result, JsonValue::Number(123.0));
-    let result = JSON::parse("-123.456e+3".to_string()).unwrap();
+    let result = JSON::parse("-123.456e+3").unwrap();
     assert_eq!(result, JsonValue::Number(-123456.0));
   }
 
+  #[test]
+  fn json_parse_number_leading_zeros() {
+    for input in ["01", "007", "00.5", "-01"] {
+      let error = JSON::parse(input).unwrap_err();
+      assert_eq!(
+        error.message,
+        format!("Invalid number '{}': leading zeros are not allowed", input)
+      );
+      assert_eq!(error.index, input.starts_with('-') as usize);
+    }
+    assert_eq!(JSON::parse("-0").unwrap(), JsonValue::Integer(0));
+    assert_eq!(JSON::parse("0").unwrap(), JsonValue::Integer(0));
+    assert_eq!(JSON::parse("0.5").unwrap(), JsonValue::Number(0.5));
+    assert_eq!(JSON::parse("1e10").unwrap(), JsonValue::Number(1e10));
+    assert_eq!(JSON::parse("10").unwrap(), JsonValue::Integer(10));
+  }
+
+  #[test]
+  fn json_parse_number_strict_grammar() {
+    let cases = [
+      (".5", 0, "expected a digit"),
+      ("5.", 2, "expected a digit after '.'"),
+      ("5.e3", 2, "expected a digit after '.'"),
+      ("1..2", 2, "expected a digit after '.'"),
+      ("1e", 2, "expected a digit in the exponent"),
+      ("1e+", 3, "expected a digit in the exponent"),
+      ("-", 1, "expected a digit"),
+      ("1.2.3", 3, "unexpected character"),
+      ("[1, 2-3]", 5, "unexpected character"),
+    ];
+    for (input, index, reason) in cases {
+      let error = JSON::parse(input).unwrap_err();
+      assert!(
+        error.message.ends_with(reason),
+        "{}: {}",
+        input,
+        error.message
+      );
+      assert_eq!(error.index, index, "{}", input);
+    }
+    assert_eq!(JSON::parse("1E-2").unwrap(), JsonValue::Number(0.01));
+  }
+
+  #[test]
+  fn json_parse_integer() {
+    let result = JSON::parse("9007199254740993").unwrap();
+    assert_eq!(result.as_i64(), Some(9007199254740993));
+    assert_eq!(
+      JSON::stringify(&result, PrettyMode::Compact),
+      "9007199254740993"
+    );
+    let result = JSON::parse("[-5,5.0,1e2,99999999999999999999]").unwrap();
+    assert_eq!(result[0].as_i64(), Some(-5));
+    assert_eq!(result[1].as_i64(), None);
+    assert_eq!(result[2].as_f64(), Some(100.0));
+    assert_eq!(result[3].as_f64(), Some(1e20));
+    assert_eq!(result[0], JsonValue::Number(-5.0));
+    assert_eq!(JsonValue::Number(5.0), JsonValue::Integer(5));
+    assert_ne!(JsonValue::Integer(5), JsonValue::Number(5.5));
+    assert_ne!(
+      JsonValue::Integer(9007199254740993),
+      JsonValue::Number(9007199254740992.0)
+    );
+    assert_ne!(
+      JsonValue::Integer(i64::MAX),
+      JsonValue::Number(i64::MAX as f64)
+    );
+    assert_eq!(
+      JsonValue::Integer(i64::MIN),
+      JsonValue::Number(i64::MIN as f64)
+    );
+    assert_ne!(JsonValue::Integer(0), JsonValue::Number(f64::NAN));
+    assert!(JsonValue::Integer(0).is_number());
+  }
+
+  #[test]
+  fn json_parse_nan_infinity() {
+    let options = ParseOptions {
+      allow_nan: true,
+      ..Default::default()
+    };
+    let result = JSON::parse_with("[NaN, Infinity, -Infinity, -1]", options).unwrap();
+    assert!(result[0].as_f64().unwrap().is_nan());
+    assert_eq!(result[1], JsonValue::Number(f64::INFINITY));
+    assert_eq!(result[2], JsonValue::Number(f64::NEG_INFINITY));
+    assert_eq!(result[3], JsonValue::Integer(-1));
+    let error = JSON::parse("NaN").unwrap_err();
+    assert_eq!(error.message, "Unexpected 'NaN'");
+    let error = JSON::parse("[-Infinity]").unwrap_err();
+    assert_eq!(error.message, "Invalid number '-': expected a digit");
+    assert_eq!(error.index, 2);
+    assert!(JSON::parse_with("nan", options).is_err());
+  }
+
+  #[test]
+  fn json_parse_comments() {
+    let input = "// settings\n{\n  \"a\": [1, /* two */ 2], // trailing\n  /* key */ \"b\" /**/: \
+                 null\n}\n// end";
+    let options = ParseOptions {
+      comments: true,
+      ..Default::default()
+    };
+    assert_eq!(
+      JSON::parse_with(input, options).unwrap(),
+      json!({"a": [1, 2], "b": null})
+    );
+    assert_eq!(
+      JSON::parse_with("1 // no newline", options).unwrap(),
+      json!(1)
+    );
+    let error = JSON::parse_with("[1] /* open", options).unwrap_err();
+    assert_eq!(
+      (error.message.as_str(), error.index),
+      ("Unterminated block comment", 4)
+    );
+    let error = JSON::parse_with("[1 / 2]", options).unwrap_err();
+    assert_eq!(error.message, "Unexpected '/'");
+    let error = JSON::parse(input).unwrap_err();
+    assert_eq!((error.message.as_str(), error.index), ("Unexpected '/'", 0));
+  }
+
+  #[test]
+  fn json_parse_trailing_commas() {
+    let options = ParseOptions {
+      trailing_commas: true,
+      ..Default::default()
+    };
+    assert_eq!(JSON::parse_with("[1,2,]", options).unwrap(), json!([1, 2]));
+    assert_eq!(
+      JSON::parse_with(r#"{"a":[{},],}"#, options).unwrap(),
+      json!({"a": [{}]})
+    );
+    let events: Result<Vec<_>, _> = JSON::events_with("[1,]", options).collect();
+    assert_eq!(events.unwrap().len(), 3);
+    for input in ["[1,,2]", "[,]", "[1,,]"] {
+      let error = JSON::parse_with(input, options).unwrap_err();
+      assert_eq!(error.message, "Expected a value");
+    }
+    assert_eq!(
+      JSON::parse_with("{,}", options).unwrap_err().message,
+      "Expected string"
+    );
+    let error = JSON::parse("[1,2,]").unwrap_err();
+    assert_eq!(
+      (error.message.as_str(), error.index),
+      ("Trailing comma is not allowed", 4)
+    );
+    let error = JSON::parse(r#"{"a":1,}"#).unwrap_err();
+    assert_eq!(
+      (error.message.as_str(), error.index),
+      ("Trailing comma is not allowed", 6)
+    );
+    assert!(JSON::events("[1,]").any(|event| event.is_err()));
+  }
+
+  #[test]
+  fn json_parse_json5() {
+    let input = r#"// JSON5 document
+{
+  unquoted: 'and you can quote me on that',
+  singleQuotes: 'I can use "double quotes" here and \'single\' ones',
+  hexadecimal: 0xdecaf,
+  negativeHex: -0x10,
+  leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+  positiveSign: +1,
+  $dollar_and_1: [Infinity, -Infinity, NaN,],
+  /* trailing comma */ "quoted": null,
+}"#;
+    let result = JSON::parse_json5(input).unwrap();
+    assert_eq!(result["unquoted"], json!("and you can quote me on that"));
+    assert_eq!(
+      result["singleQuotes"],
+      json!("I can use \"double quotes\" here and 'single' ones")
+    );
+    assert_eq!(result["hexadecimal"], JsonValue::Integer(0xdecaf));
+    assert_eq!(result["negativeHex"], JsonValue::Integer(-16));
+    assert_eq!(result["leadingDecimalPoint"], json!(0.8675309));
+    assert_eq!(result["andTrailing"], json!(8675309.0));
+    assert_eq!(result["positiveSign"], JsonValue::Integer(1));
+    assert_eq!(
+      result["$dollar_and_1"][1],
+      JsonValue::Number(f64::NEG_INFINITY)
+    );
+    assert!(result["$dollar_and_1"][2].as_f64().unwrap().is_nan());
+    assert_eq!(result["quoted"], JsonValue::Null);
+    let events: Result<Vec<_>, _> = JSON::events_with(input, ParseOptions::json5()).collect();
+    assert_eq!(events.unwrap().len(), 24);
+    assert!(JSON::parse(input).is_err());
+
+    let error = JSON::parse_json5("[name]").unwrap_err();
+    assert_eq!(
+      (error.message.as_str(), error.index),
+      ("Unexpected 'name'", 1)
+    );
+    assert_eq!(
+      JSON::parse_json5("0x").unwrap_err().message,
+      "Invalid number '0x': expected a hex digit"
+    );
+    assert_eq!(
+      JSON::parse_json5(".").unwrap_err().message,
+      "Invalid number '.': expected a digit"
+    );
+    assert!(JSON::parse_json5("0xFFFFFFFFFFFFFFFFF").is_err());
+
+    let input = "{null: 1, true: 2, false: 3, Infinity: 4, NaN: null, nullable: true}";
+    assert_eq!(
+      JSON::parse_json5(input).unwrap(),
+      json!({"null": 1, "true": 2, "false": 3, "Infinity": 4, "NaN": null, "nullable": true})
+    );
+    let keys: Vec<_> = JSON::events_with(input, ParseOptions::json5())
+      .filter_map(|event| match event.unwrap() {
+        JsonEvent::Key(key) => Some(key),
+        _ => None,
+      })
+      .collect();
+    assert_eq!(keys, [
+      "null", "true", "false", "Infinity", "NaN", "nullable"
+    ]);
+    assert!(JSON::parse_json5("{-Infinity: 1}").is_err());
+    assert!(JSON::parse("{null: 1}").is_err());
+  }
+
+  #[test]
+  fn json_parse_json5_options() {
+    let options = ParseOptions {
+      unquoted_keys: true,
+      ..Default::default()
+    };
+    assert_eq!(
+      JSON::parse_with("{a_1: true}", options).unwrap(),
+      json!({"a_1": true})
+    );
+    assert!(JSON::parse_with("{'a': 1}", options).is_err());
+    assert!(JSON::parse_with("[0x1]", options).is_err());
+    let options = ParseOptions {
+      single_quotes: true,
+      ..Default::default()
+    };
+    assert_eq!(
+      JSON::parse_with("['a\"b']", options).unwrap(),
+      json!(["a\"b"])
+    );
+    assert!(JSON::parse_with("{a: 1}", options).is_err());
+    let options = ParseOptions {
+      hex_numbers: true,
+      ..Default::default()
+    };
+    assert_eq!(JSON::parse_with("0XfF", options).unwrap(), json!(255));
+    assert!(JSON::parse_with(".5", options).is_err());
+    let options = ParseOptions {
+      lenient_numbers: true,
+      ..Default::default()
+    };
+    assert_eq!(
+      JSON::parse_with("[-.5, 1.e2]", options).unwrap(),
+      json!([-0.5, 100.0])
+    );
+    assert!(JSON::parse_with("+Infinity", options).is_err());
+    assert!(JSON::parse_with("01", options).is_err());
+    for input in ["{a: 1}", "'a'", "0xFF", ".5", "5.", "+1"] {
+      assert!(JSON::parse(input).is_err(), "{}", input);
+    }
+  }
+
+  #[test]
+  fn json_parse_hex_numbers() {
+    let options = ParseOptions {
+      hex_numbers: true,
+      ..Default::default()
+    };
+    for (input, expected) in [("0xFF", 255), ("0x0", 0), ("-0x10", -16), ("0x1a", 26)] {
+      assert_eq!(
+        JSON::parse_with(input, options).unwrap(),
+        JsonValue::Integer(expected)
+      );
+    }
+    let error = JSON::parse("0xFF").unwrap_err();
+    assert_eq!((error.message.as_str(), error.index), ("Unexpected 'x'", 1));
+    assert_eq!(
+      JSON::parse_with("0xG", options).unwrap_err().message,
+      "Invalid number '0xG'"
+    );
+  }
+
+  #[test]
+  fn json_parse_partial() {
+    let input = "[1,2] rest";
+    let (value, end) = JSON::parse_partial(input).unwrap();
+    assert_eq!((value, end), (json!([1, 2]), 5));
+    assert_eq!(&input[end..], " rest");
+    assert_eq!(
+      JSON::parse_partial(" {\"a\": [{}]}{\"b\"").unwrap(),
+      (json!({"a": [{}]}), 12)
+    );
+    assert_eq!(JSON::parse_partial("\"x\"\n!").unwrap(), (json!("x"), 3));
+    assert_eq!(JSON::parse_partial("12,").unwrap(), (json!(12), 2));
+    for (input, message, index) in [
+      ("[1, 2", "Unexpected end of input", 5),
+      ("[1}", "Expected ',' or ']'", 2),
+      ("", "Unexpected end of input", 0),
+      ("] 1", "Unexpected ']'", 0),
+    ] {
+      let error = JSON::parse_partial(input).unwrap_err();
+      assert_eq!((error.message.as_str(), error.index), (message, index));
+    }
+  }
+
+  #[test]
+  fn json_parse_many() {
+    assert_eq!(JSON::parse_many("1 2\n\t3 ").unwrap(), vec![
+      json!(1),
+      json!(2),
+      json!(3)
+    ]);
+    assert_eq!(
+      JSON::parse_many(r#"{"a":1}{"b":2}[3]"x"null"#).unwrap(),
+      vec![
+        json!({"a": 1}),
+        json!({"b": 2}),
+        json!([3]),
+        json!("x"),
+        json!(null)
+      ]
+    );
+    assert_eq!(JSON::parse_many("  ").unwrap(), vec![]);
+    let error = JSON::parse_many("{\"a\":1}\n{\"b\":}").unwrap_err();
+    assert_eq!(
+      (
+        error.message.as_str(),
+        error.index,
+        error.line,
+        error.column
+      ),
+      ("Unexpected '}'", 13, 2, 6)
+    );
+    let error = JSON::parse_many("[1] [2").unwrap_err();
+    assert_eq!(
+      (error.message.as_str(), error.index),
+      ("Unexpected end of input", 6)
+    );
+  }
+
+  #[test]
+  fn json_parse_lines() {
+    let input = "{\"id\":1}\n\n{\"id\":2}\r\n  \n{\"id\":}\n{\"id\":3}";
+    let results: Vec<_> = JSON::lines(input).collect();
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].as_ref().unwrap(), &json!({"id": 1}));
+    assert_eq!(results[1].as_ref().unwrap(), &json!({"id": 2}));
+    assert_eq!(results[3].as_ref().unwrap(), &json!({"id": 3}));
+    let error = results[2].as_ref().unwrap_err();
+    assert_eq!(error.message, "Unexpected '}'");
+    assert_eq!((error.line, error.column, error.index), (5, 7, 29));
+
+    let error = JSON::parse_lines(input).unwrap_err();
+    assert_eq!(error.line, 5);
+    let values = JSON::parse_lines("1\n[2]\n\"3\"\n").unwrap();
+    assert_eq!(values, vec![json!(1), json!([2]), json!("3")]);
+    assert!(JSON::parse_lines("").unwrap().is_empty());
+  }
+
+  #[test]
+  fn json_parse_bytes() {
+    let bytes = "{\"name\": \"caf\u{e9}\"}".as_bytes().to_vec();
+    assert_eq!(
+      JSON::parse_bytes(&bytes).unwrap(),
+      json!({"name": "caf\u{e9}"})
+    );
+    let error = JSON::parse_bytes(b"[\"ab\xff\"]").unwrap_err();
+    assert_eq!((error.message.as_str(), error.index), ("Invalid UTF-8", 4));
+    assert_eq!(error.kind, ErrorKind::Encoding);
+    assert!(!error.kind.is_syntax());
+    let error = JSON::parse_bytes(b"[1,]").unwrap_err();
+    assert_eq!(error.message, "Trailing comma is not allowed");
+    assert!(error.kind.is_syntax());
+  }
+
+  #[test]
+  fn json_parse_bom() {
+    let input = "\u{feff}{\"a\": [1]}";
+    assert_eq!(JSON::parse(input).unwrap(), json!({"a": [1]}));
+    assert_eq!(
+      JSON::parse_bytes(b"\xef\xbb\xbf[true]").unwrap(),
+      json!([true])
+    );
+    let reader = std::io::Cursor::new(input.as_bytes());
+    assert_eq!(JSON::parse_reader(reader).unwrap(), json!({"a": [1]}));
+    assert!(JSON::validate(input).is_ok());
+    assert_eq!(JSON::parse_many("\u{feff}1 2").unwrap(), [
+      json!(1),
+      json!(2)
+    ]);
+    let error = JSON::parse("\u{feff}[1,").unwrap_err();
+    assert_eq!((error.index, error.column), (6, 4));
+    assert!(error.with_context("\u{feff}[1,").ends_with("\n  |    ^"));
+    for input in [
+      " \u{feff}1",
+      "[\u{feff}1]",
+      "\u{feff}\u{feff}1",
+      "1 \u{feff}",
+    ] {
+      let error = JSON::parse(input).unwrap_err();
+      assert_eq!(error.kind, ErrorKind::UnexpectedChar, "{:?}", input);
+    }
+    assert!(JSON::parse_lines("1\n\u{feff}2").is_err());
+    assert!(JSON::parse_many("1 \u{feff}2").is_err());
+  }
+
+  #[test]
+  fn json_parse_reader() {
+    let reader = std::io::Cursor::new(b"{\"a\": [1, 2]}".to_vec());
+    assert_eq!(JSON::parse_reader(reader).unwrap(), json!({"a": [1, 2]}));
+    let error = JSON::parse_reader(std::io::Cursor::new(b"[1 2]")).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::UnexpectedToken);
+    let error = JSON::parse_reader(std::io::Cursor::new(b"\"\xc3\"")).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Encoding);
+
+    struct Broken;
+    impl std::io::Read for Broken {
+      fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::new(
+          std::io::ErrorKind::ConnectionReset,
+          "reset",
+        ))
+      }
+    }
+    let error = JSON::parse_reader(Broken).unwrap_err();
+    assert_eq!(
+      error.kind,
+      ErrorKind::Io(std::io::ErrorKind::ConnectionReset)
+    );
+    assert_eq!(error.to_string(), "ERROR: reset while reading JSON");
+    assert!(!error.kind.is_syntax());
+    use std::error::Error as _;
+    let source = error.source().unwrap();
+    let source = source.downcast_ref::<std::io::Error>().unwrap();
+    assert_eq!(source.kind(), std::io::ErrorKind::ConnectionReset);
+    assert_eq!(source.to_string(), "reset");
+    assert!(JSON::parse("[").unwrap_err().source().is_none());
+  }
+
   #[test]
   fn json_parse_string() {
-    let result = JSON::parse(r#""hello""#.to_string()).unwrap();
+    let result = JSON::parse(r#""hello""#).unwrap();
     assert_eq!(result, JsonValue::String(r#"hello"#.to_string()));
-    let result = JSON::parse(r#""hello\\ world""#.to_string()).unwrap();
+    let result = JSON::parse(r#""hello\\ world""#).unwrap();
     assert_eq!(result, JsonValue::String(r#"hello\ world"#.to_string()));
   }
 
+  #[test]
+  fn json_parse_raw_numbers() {
+    let options = ParseOptions {
+      number_mode: NumberMode::Raw,
+      ..Default::default()
+    };
+    for input in [
+      "9007199254740993",
+      "0.30000000000000004",
+      "[123456789012345678901234567890, -0, 1E400, 3.14159265358979323846264338327950288]",
+    ] {
+      let value = JSON::parse_with(input, options).unwrap();
+      assert_eq!(
+        JSON::stringify(&value, PrettyMode::Compact),
+        input.replace(", ", ",")
+      );
+    }
+    let value = JSON::parse_with("9007199254740993", options).unwrap();
+    assert_eq!(value, JsonValue::RawNumber("9007199254740993".to_string()));
+    assert_eq!(value.as_i64(), Some(9007199254740993));
+    assert_eq!(value, JsonValue::Integer(9007199254740993));
+    assert_ne!(value, JsonValue::Integer(9007199254740992));
+    assert!(value.is_number() && value > json!(9007199254740992_i64));
+    assert_eq!(
+      JSON::parse_with("1.0", options).unwrap(),
+      JSON::parse_with("1", options).unwrap()
+    );
+    assert_eq!(
+      JSON::parse_with("1e2", options)
+        .unwrap()
+        .partial_cmp(&JSON::parse_with("100.0", options).unwrap()),
+      Some(std::cmp::Ordering::Equal)
+    );
+    assert!(JSON::parse_with("-0.5", options).unwrap() < JSON::parse_with("-0", options).unwrap());
+
+    let lenient = ParseOptions {
+      lenient_numbers: true,
+      ..options
+    };
+    assert_eq!(
+      JSON::parse_with("[+1, .5]", lenient).unwrap(),
+      json!([1, 0.5])
+    );
+    assert_eq!(
+      JSON::parse_with("[+1, .5]", lenient).unwrap()[0],
+      JsonValue::Integer(1)
+    );
+  }
+
+  #[test]
+  fn json_parse_string_unterminated() {
+    let error = JSON::parse(r#"["ok", "hello]"#).unwrap_err();
+    assert_eq!(error.message, "Unterminated string");
+    assert_eq!(error.index, 7);
+    let error = JSON::parse_json5("{a: 'x}").unwrap_err();
+    assert_eq!(
+      (error.message.as_str(), error.index),
+      ("Unterminated string", 4)
+    );
+    assert!(JSON::validate(r#"{"key"#).is_err());
+  }
+
+  #[test]
+  fn json_parse_string_control_characters() {
+    let error = JSON::parse("[\"line\nbreak\"]").unwrap_err();
+    assert_eq!(error.message, "Unescaped control character");
+    assert_eq!((error.index, error.line, error.column), (6, 1, 7));
+    assert!(JSON::parse("\"tab\there\"").is_err());
+    let options = ParseOptions {
+      control_characters: true,
+      ..Default::default()
+    };
+    let result = JSON::parse_with("\"line\nbreak\"", options).unwrap();
+    assert_eq!(result, JsonValue::String("line\nbreak".to_string()));
+  }
+
+  #[test]
+  fn json_parse_string_unicode_escape() {
+    let result = JSON::parse(r#""\u0041\u00e9""#).unwrap();
+    assert_eq!(result, JsonValue::String("Aé".to_string()));
+    let result = JSON::parse(r#""\u00E9""#).unwrap();
+    assert_eq!(result, JsonValue::String("é".to_string()));
+    let error = JSON::parse(r#""\u00g9""#).unwrap_err();
+    assert_eq!(error.message, "Invalid unicode escape");
+    assert_eq!(error.index, 1);
+    let error = JSON::parse(r#""\u00""#).unwrap_err();
+    assert_eq!(error.message, "Invalid unicode escape");
+  }
+
+  #[test]
+  fn json_parse_string_surrogate_pair() {
+    let result = JSON::parse(r#""\uD83D\uDE00""#).unwrap();
+    assert_eq!(result, JsonValue::String("😀".to_string()));
+    let error = JSON::parse(r#""\uD83D""#).unwrap_err();
+    assert_eq!(error.message, "Unpaired high surrogate");
+    assert_eq!(error.index, 1);
+    let error = JSON::parse(r#""a\uDE00""#).unwrap_err();
+    assert_eq!(error.message, "Unpaired low surrogate");
+    assert_eq!(error.index, 2);
+    let error = JSON::parse(r#""\uD83D\n""#).unwrap_err();
+    assert_eq!(error.message, "Expected low surrogate after high surrogate");
+    assert_eq!(error.index, 7);
+  }
+
+  #[test]
+  fn json_parse_trailing_content() {
+    let error = JSON::parse("1 2").unwrap_err();
+    assert_eq!(error.message, "Unexpected trailing content");
+    assert_eq!(error.index, 2);
+    let error = JSON::parse("null null").unwrap_err();
+    assert_eq!(error.message, "Unexpected trailing content");
+    assert_eq!(error.index, 5);
+    let error = JSON::parse("{} garbage").unwrap_err();
+    assert_eq!(error.message, "Unexpected 'garbage'");
+    let result = JSON::parse(" [1] ").unwrap();
+    assert_eq!(result, JsonValue::Array(vec![JsonValue::Number(1.0)]));
+  }
+
+  #[test]
+  fn json_parse_unterminated_container() {
+    for (input, index) in [
+      (r#"{"a":1"#, 6),
+      ("[1,2", 4),
+      (r#"{"a":"#, 5),
+      (r#"{"a""#, 4),
+      ("{", 1),
+      ("[[]", 3),
+      ("[1,2 ", 5),
+    ] {
+      let error = JSON::parse(input).unwrap_err();
+      assert_eq!(
+        (error.message.as_str(), error.index),
+        ("Unexpected end of input", index),
+        "{}",
+        input
+      );
+      let event_error = JSON::events(input).find_map(Result::err).unwrap();
+      assert_eq!(event_error.message, error.message);
+      assert_eq!(event_error.index, error.index);
+    }
+  }
+
+  #[test]
+  fn json_parse_array_error_index() {
+    for (input, index) in [
+      ("[1 2]", 3),
+      ("[1,\n  true false]", 11),
+      (r#"{"a":[[],[] {}]}"#, 12),
+    ] {
+      let error = JSON::parse(input).unwrap_err();
+      assert_eq!(
+        (error.message.as_str(), error.index),
+        ("Expected ',' or ']'", index),
+        "{}",
+        input
+      );
+    }
+    let error = JSON::parse("[1,\n  true false]").unwrap_err();
+    assert_eq!((error.line, error.column), (2, 8));
+  }
+
+  #[test]
+  fn json_parse_object_error_index() {
+    for (input, message, index) in [
+      (r#"{"a" 1}"#, "Expected ':'", 5),
+      (r#"{"a":1 2}"#, "Expected ',' or '}'", 7),
+      (r#"[0, {"a":{"b" "c"}}]"#, "Expected ':'", 14),
+      (r#"[0, {"a":{"b":1]}}"#, "Expected ',' or '}'", 15),
+      (r#"{1:2}"#, "Expected string", 1),
+    ] {
+      let error = JSON::parse(input).unwrap_err();
+      assert_eq!(
+        (error.message.as_str(), error.index),
+        (message, index),
+        "{}",
+        input
+      );
+    }
+  }
+
+  #[test]
+  fn json_parse_error_location() {
+    let error = JSON::parse("{\n  \"a\": 1,\n  \"b\": tru\n}").unwrap_err();
+    assert_eq!(error.index, 19);
+    assert_eq!((error.line, error.column), (3, 8));
+    assert_eq!(
+      error.to_string(),
+      "ERROR: Unexpected 'tru' in JSON at line 3, column 8"
+    );
+    let error = JSON::parse("[\r\n1,\r\n:]").unwrap_err();
+    assert_eq!((error.line, error.column), (3, 1));
+    let error = JSON::parse("\n\n  x").unwrap_err();
+    assert_eq!((error.index, error.line, error.column), (4, 3, 3));
+  }
+
+  #[test]
+  fn json_error_kinds() {
+    for (input, kind) in [
+      ("[1, @]", ErrorKind::UnexpectedChar),
+      ("[1 2]", ErrorKind::UnexpectedToken),
+      ("{\"a\": nul}", ErrorKind::UnexpectedToken),
+      ("[1,]", ErrorKind::UnexpectedToken),
+      ("\"abc", ErrorKind::UnterminatedString),
+      ("[01]", ErrorKind::InvalidNumber),
+      ("\"\\q\"", ErrorKind::InvalidEscape),
+      ("\"\\ud800\"", ErrorKind::InvalidEscape),
+      ("{\"a\": 1, \"a\": 2}", ErrorKind::DuplicateKey),
+      ("{} []", ErrorKind::TrailingContent),
+      ("{\"a\": [", ErrorKind::UnexpectedEof),
+      (&"[".repeat(200), ErrorKind::DepthExceeded),
+    ] {
+      let error = JSON::parse(input).unwrap_err();
+      assert_eq!(error.kind, kind, "{}", input);
+      // Validation does not look for duplicate keys.
+      if kind != ErrorKind::DuplicateKey {
+        assert_eq!(JSON::validate(input).unwrap_err().kind, kind, "{}", input);
+      }
+    }
+    let options = ParseOptions {
+      max_string_len: Some(2),
+      ..Default::default()
+    };
+    let error = JSON::parse_with("\"abc\"", options).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::LimitExceeded);
+    assert_eq!(
+      toml::TOML::parse("a = ").unwrap_err().kind,
+      ErrorKind::Syntax
+    );
+  }
+
+  #[test]
+  fn json_error_with_context() {
+    let source = "{\n  \"a\": 1,\n\t\"b\": tru\n}";
+    let error = JSON::parse(source).unwrap_err();
+    assert_eq!(
+      error.with_context(source),
+      "ERROR: Unexpected 'tru' in JSON at line 3, column 7\n  |\n3 | \t\"b\": tru\n  | \t     ^"
+    );
+    let source = format!("{}[1,,2]", "\n".repeat(9));
+    let error = JSON::parse(&source).unwrap_err();
+    assert_eq!(
+      error
+        .with_context(&source)
+        .lines()
+        .skip(1)
+        .collect::<Vec<_>>(),
+      ["   |", "10 | [1,,2]", "   |    ^"]
+    );
+    let error = JSON::parse("[1, 2").unwrap_err();
+    assert!(error
+      .with_context("[1, 2")
+      .ends_with("1 | [1, 2\n  |      ^"));
+  }
+
+  #[test]
+  fn json_parse_large_array() {
+    let input = format!("[{}1]", "1,".repeat(200_000));
+    let result = JSON::parse(&input).unwrap();
+    assert_eq!(result.as_array().map(Vec::len), Some(200_001));
+    let error = JSON::parse("[\"é\", x]").unwrap_err();
+    assert_eq!((error.index, error.column), (7, 7));
+  }
+
+  #[test]
+  fn json_parse_max_depth() {
+    let error = JSON::parse("[".repeat(100_000)).unwrap_err();
+    assert_eq!(error.message, "Maximum nesting depth exceeded");
+    assert_eq!(error.index, 128);
+    let options = ParseOptions {
+      max_depth: 2,
+      ..Default::default()
+    };
+    assert!(JSON::parse_with("[{\"a\":1}]", options).is_ok());
+    let error = JSON::parse_with("[{\"a\":[]}]", options).unwrap_err();
+    assert_eq!(
+      (error.message.as_str(), error.index),
+      ("Maximum nesting depth exceeded", 6)
+    );
+  }
+
+  #[test]
+  fn json_parse_max_string_len() {
+    let options = ParseOptions {
+      max_string_len: Some(5),
+      ..Default::default()
+    };
+    assert_eq!(
+      JSON::parse_with(r#"{"abc": "\u00e9\n12"}"#, options).unwrap(),
+      json!({"abc": "é\n12"})
+    );
+    for (input, index) in [(r#"["abcdef"]"#, 1), (r#"{"key123": 1}"#, 1)] {
+      let error = JSON::parse_with(input, options).unwrap_err();
+      assert_eq!(
+        (error.message.as_str(), error.index),
+        ("String exceeds maximum length", index)
+      );
+    }
+    assert!(JSON::parse(format!("\"{}\"", "a".repeat(100_000))).is_ok());
+  }
+
+  #[test]
+  fn json_parse_max_elements() {
+    let options = ParseOptions {
+      max_elements: Some(4),
+      ..Default::default()
+    };
+    assert!(JSON::parse_with(r#"{"a": [1, 2]}"#, options).is_ok());
+    let error = JSON::parse_with(r#"{"a": [1, 2, 3]}"#, options).unwrap_err();
+    assert_eq!(
+      (error.message.as_str(), error.index),
+      ("Maximum number of elements exceeded", 13)
+    );
+    let bomb = format!("[{}0]", "0,".repeat(100_000));
+    let options = ParseOptions {
+      max_elements: Some(1_000),
+      ..Default::default()
+    };
+    let error = JSON::parse_with(&bomb, options).unwrap_err();
+    assert_eq!(error.message, "Maximum number of elements exceeded");
+    assert_eq!(JSON::parse(&bomb).unwrap().len(), 100_001);
+  }
+
   #[test]
   fn json_parse_array() {
-    let result = JSON::parse("[null,true,123,\"hello\"]".to_string()).unwrap();
+    let result = JSON::parse("[null,true,123,\"hello\"]").unwrap();
     assert_eq!(
       result,
       JsonValue::Array(vec![
@@ -52,7 +932,7 @@ mod json_tests {
         JsonValue::String("hello".to_string())
       ])
     );
-    let result = JSON::parse("[42,[true],\"a\"]".to_string()).unwrap();
+    let result = JSON::parse("[42,[true],\"a\"]").unwrap();
     assert_eq!(
       result,
       JsonValue::Array(vec![
@@ -65,20 +945,20 @@ mod json_tests {
 
   #[test]
   fn json_parse_object() {
-    let result = JSON::parse(r#"{"a":null,"b":true,"c":123,"d":"hello"}"#.to_string()).unwrap();
+    let result = JSON::parse(r#"{"a":null,"b":true,"c":123,"d":"hello"}"#).unwrap();
     assert_eq!(
       result,
-      JsonValue::Object(HashMap::from([
+      JsonValue::Object(JsonMap::from([
         ("a".to_string(), JsonValue::Null),
         ("b".to_string(), JsonValue::Boolean(true)),
         ("c".to_string(), JsonValue::Number(123.0)),
         ("d".to_string(), JsonValue::String("hello".to_string()))
       ]))
     );
-    let result = JSON::parse(r#"{"a":42,"b":[true],"c":"a"}"#.to_string()).unwrap();
+    let result = JSON::parse(r#"{"a":42,"b":[true],"c":"a"}"#).unwrap();
     assert_eq!(
       result,
-      JsonValue::Object(HashMap::from([
+      JsonValue::Object(JsonMap::from([
         ("a".to_string(), JsonValue::Number(42.0)),
         (
           "b".to_string(),
@@ -89,78 +969,2140 @@ mod json_tests {
     );
   }
 
-  // --------------------------------
+  #[test]
+  fn json_parse_duplicate_keys() {
+    let input = r#"{"a":1,"b":0,"a":2}"#;
+    let error = JSON::parse(input).unwrap_err();
+    assert_eq!(
+      (error.message.as_str(), error.index),
+      ("Duplicate key 'a'", 13)
+    );
+    let parse = |duplicate_keys| {
+      JSON::parse_with(input, ParseOptions {
+        duplicate_keys,
+        ..Default::default()
+      })
+    };
+    assert!(parse(DuplicateKeyPolicy::Error).is_err());
+    let first = parse(DuplicateKeyPolicy::First).unwrap();
+    assert_eq!(
+      JSON::stringify(&first, PrettyMode::Compact),
+      r#"{"a":1,"b":0}"#
+    );
+    let last = parse(DuplicateKeyPolicy::Last).unwrap();
+    assert_eq!(
+      JSON::stringify(&last, PrettyMode::Compact),
+      r#"{"a":2,"b":0}"#
+    );
+  }
 
   #[test]
-  fn json_stringify_null() {
-    let result = JSON::stringify(JsonValue::Null, 0);
-    assert_eq!(result, "null");
+  fn json_parse_with_default_options() {
+    let options = ParseOptions::default();
+    assert_eq!(options.max_depth, 128);
+    assert_eq!(options.duplicate_keys, DuplicateKeyPolicy::Error);
+    let inputs = [
+      r#"{"a":[1,2.5,"x",null,true]}"#,
+      "[1,]",
+      "// c\n1",
+      "NaN",
+      "{a:1}",
+      "'s'",
+      "0x1",
+      ".5",
+      r#"{"a":1,"a":2}"#,
+    ];
+    for input in inputs {
+      let strict = JSON::parse_with(input, options);
+      match JSON::parse(input) {
+        Ok(value) => assert_eq!(strict.unwrap(), value),
+        Err(error) => assert_eq!(strict.unwrap_err().message, error.message),
+      }
+    }
+    let json5 = ParseOptions::json5();
+    for input in &inputs[1..8] {
+      assert!(JSON::parse_with(input, json5).is_ok(), "{}", input);
+    }
   }
 
   #[test]
-  fn json_stringify_boolean() {
-    let result = JSON::stringify(JsonValue::Boolean(true), 0);
-    assert_eq!(result, "true");
-    let result = JSON::stringify(JsonValue::Boolean(false), 0);
-    assert_eq!(result, "false");
+  fn json_parse_collect() {
+    let input = "{\n  \"a\": [1, tru, 3],\n  \"b\" 2,\n  \"c\": {\"d\": 4,}\n}";
+    let (value, errors) = JSON::parse_collect(input);
+    assert_eq!(value.unwrap(), json!({"a": [1, 3], "c": {"d": 4}}));
+    let found: Vec<_> = errors
+      .iter()
+      .map(|error| (error.message.as_str(), error.line, error.column))
+      .collect();
+    assert_eq!(found, [
+      ("Unexpected 'tru'", 2, 12),
+      ("Expected ':'", 3, 7),
+      ("Trailing comma is not allowed", 4, 15),
+    ]);
+
+    let (value, errors) = JSON::parse_collect("[[1 2], {\"x\": }, 01, 4] 5");
+    assert_eq!(value.unwrap(), json!([[1], {}, 4]));
+    let messages: Vec<_> = errors.iter().map(|error| error.message.as_str()).collect();
+    assert_eq!(messages, [
+      "Expected ',' or ']'",
+      "Unexpected '}'",
+      "Invalid number '01': leading zeros are not allowed",
+      "Unexpected trailing content",
+    ]);
+
+    let (value, errors) = JSON::parse_collect("[1, 2");
+    assert!(value.is_none());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "Unexpected end of input");
+    let (value, errors) = JSON::parse_collect(r#"{"a":[true]}"#);
+    assert_eq!(value.unwrap(), json!({"a": [true]}));
+    assert!(errors.is_empty());
   }
 
   #[test]
-  fn json_stringify_number() {
-    let result = JSON::stringify(JsonValue::Number(123.0), 0);
-    assert_eq!(result, "123");
-    let result = JSON::stringify(JsonValue::Number(-123.456), 0);
-    assert_eq!(result, "-123.456");
+  fn json_parse_validate() {
+    assert!(JSON::validate(r#"{"a":[1,2.5,{"b":null}],"c":"\u00e9"}"#).is_ok());
+    assert!(JSON::validate(" 0 ").is_ok());
+    for (input, message, index) in [
+      ("", "Unexpected end of input", 0),
+      ("[1,2", "Unexpected end of input", 4),
+      ("[1] [2]", "Unexpected trailing content", 4),
+      (r#"{"a" 1}"#, "Expected ':'", 5),
+      (
+        "[01]",
+        "Invalid number '01': leading zeros are not allowed",
+        1,
+      ),
+      ("[tru]", "Unexpected 'tru'", 1),
+    ] {
+      let error = JSON::validate(input).unwrap_err();
+      assert_eq!(
+        (error.message.as_str(), error.index),
+        (message, index),
+        "{}",
+        input
+      );
+      assert_eq!(JSON::parse(input).unwrap_err().message, error.message);
+    }
   }
 
   #[test]
-  fn json_stringify_string() {
-    let result = JSON::stringify(JsonValue::String("hello".to_string()), 0);
-    assert_eq!(result, "\"hello\"");
-    let result = JSON::stringify(JsonValue::String("hello\\ world\n".to_string()), 0);
-    assert_eq!(result, "\"hello\\\\ world\\n\"");
+  fn json_parse_borrowed() {
+    let input = String::from("[true]");
+    let result = JSON::parse(&input).unwrap();
+    assert_eq!(result, JsonValue::Array(vec![JsonValue::Boolean(true)]));
+    assert_eq!(JSON::parse(input.as_str()).unwrap(), result);
+    assert_eq!(JSON::parse("null").unwrap(), JsonValue::Null);
   }
 
   #[test]
-  fn json_stringify_array() {
-    let result = JSON::stringify(
-      JsonValue::Array(vec![
-        JsonValue::Null,
-        JsonValue::Boolean(true),
-        JsonValue::Number(123.0),
-        JsonValue::String("hello".to_string()),
-      ]),
-      0,
+  fn json_parse_from_str() {
+    assert_eq!(
+      "true".parse::<JsonValue>().unwrap(),
+      JsonValue::Boolean(true)
     );
-    assert_eq!(result, "[null,true,123,\"hello\"]");
-    let result = JSON::stringify(
-      JsonValue::Array(vec![
-        JsonValue::Number(42.0),
-        JsonValue::Array(vec![JsonValue::Boolean(true)]),
-        JsonValue::String("a".to_string()),
-      ]),
-      0,
+    let result: JsonValue = "[1]".parse().unwrap();
+    assert_eq!(result, JsonValue::Array(vec![JsonValue::Number(1.0)]));
+    assert!("[1".parse::<JsonValue>().is_err());
+    let owned = String::from("{\"a\":[]}");
+    assert_eq!(owned.parse::<JsonValue>().unwrap(), json!({"a": []}));
+    assert_eq!(JsonValue::try_parse("[1]").unwrap(), json!([1]));
+    assert_eq!(JsonValue::try_parse(owned).unwrap(), json!({"a": []}));
+    assert_eq!(
+      JsonValue::try_parse(String::from("[1"))
+        .unwrap_err()
+        .message,
+      "[1".parse::<JsonValue>().unwrap_err().message
     );
-    assert_eq!(result, "[42,[true],\"a\"]");
+    assert!(JsonValue::try_parse("").is_err());
+  }
+
+  #[test]
+  fn json_parse_preserves_key_order() {
+    let input = r#"{"z":1,"a":2,"m":{"y":null,"b":[]}}"#;
+    let result = JSON::parse(input).unwrap();
+    assert_eq!(JSON::stringify(&result, PrettyMode::Compact), input);
+    let keys: Vec<_> = result.as_object().unwrap().keys().cloned().collect();
+    assert_eq!(keys, ["z", "a", "m"]);
+    assert_eq!(result["a"], JsonValue::Number(2.0));
+  }
+
+  #[test]
+  fn json_map() {
+    let mut map = JsonMap::new();
+    assert_eq!(map.insert("b".to_string(), JsonValue::Null), None);
+    map.insert("a".to_string(), JsonValue::Boolean(true));
+    map.insert("c".to_string(), JsonValue::Number(1.0));
+    assert_eq!(
+      map.insert("b".to_string(), JsonValue::Number(2.0)),
+      Some(JsonValue::Null)
+    );
+    assert_eq!(map.remove("a"), Some(JsonValue::Boolean(true)));
+    assert_eq!(map.remove("a"), None);
+    assert_eq!(map.get("c"), Some(&JsonValue::Number(1.0)));
+    let entries: Vec<_> = map.clone().into_iter().collect();
+    assert_eq!(entries, [
+      ("b".to_string(), JsonValue::Number(2.0)),
+      ("c".to_string(), JsonValue::Number(1.0))
+    ]);
+    let reversed: JsonMap = entries.into_iter().rev().collect();
+    assert_eq!(map, reversed);
+  }
+
+  #[test]
+  fn json_events() {
+    let events: Result<Vec<_>, _> = JSON::events(r#"{"a":[1,"x",{}],"b":null}"#).collect();
+    assert_eq!(events.unwrap(), [
+      JsonEvent::StartObject,
+      JsonEvent::Key("a".to_string()),
+      JsonEvent::StartArray,
+      JsonEvent::Value(JsonValue::Integer(1)),
+      JsonEvent::Value(JsonValue::String("x".to_string())),
+      JsonEvent::StartObject,
+      JsonEvent::EndObject,
+      JsonEvent::EndArray,
+      JsonEvent::Key("b".to_string()),
+      JsonEvent::Value(JsonValue::Null),
+      JsonEvent::EndObject,
+    ]);
+    let total: i64 = JSON::events("[1,2,3,4]")
+      .filter_map(|event| match event.unwrap() {
+        JsonEvent::Value(value) => value.as_i64(),
+        _ => None,
+      })
+      .sum();
+    assert_eq!(total, 10);
+  }
+
+  #[test]
+  fn json_events_errors() {
+    let cases = [
+      ("[1 2]", "Expected ',' or ']'", 3),
+      ("{\"a\" 1}", "Expected ':'", 5),
+      ("{1:2}", "Expected string", 1),
+      ("[1,]", "Expected a value", 3),
+      ("[1", "Unexpected end of input", 2),
+      ("1 2", "Unexpected trailing content", 2),
+    ];
+    for (input, message, index) in cases {
+      let mut events = JSON::events(input);
+      let error = events.find_map(Result::err).unwrap();
+      assert_eq!(
+        (error.message.as_str(), error.index),
+        (message, index),
+        "{}",
+        input
+      );
+      assert!(events.next().is_none());
+    }
+    let error = JSON::events(&"[".repeat(200))
+      .find_map(Result::err)
+      .unwrap();
+    assert_eq!(error.message, "Maximum nesting depth exceeded");
+  }
+
+  // --------------------------------
+
+  #[test]
+  fn json_value_type_name() {
+    assert_eq!(JsonValue::Null.type_name(), "null");
+    assert_eq!(JsonValue::Boolean(true).type_name(), "boolean");
+    assert_eq!(JsonValue::Number(1.5).type_name(), "number");
+    assert_eq!(JsonValue::Integer(1).type_name(), "number");
+    assert_eq!(JsonValue::String("x".to_string()).type_name(), "string");
+    assert_eq!(json!([1]).type_name(), "array");
+    assert_eq!(json!({}).type_name(), "object");
+  }
+
+  #[test]
+  fn json_value_get() {
+    let value = JSON::parse(r#"{"a":[1,{"b":null}]}"#).unwrap();
+    assert_eq!(
+      value
+        .get("a")
+        .and_then(|a| a.get_index(1))
+        .and_then(|o| o.get("b")),
+      Some(&JsonValue::Null)
+    );
+    assert_eq!(value.get("missing"), None);
+    assert_eq!(value.get_index(0), None);
+    assert_eq!(value["a"].get_index(2), None);
+    assert_eq!(value["a"].get("b"), None);
+    assert_eq!(JsonValue::Null.get("a"), None);
   }
 
-  // Cannot predict order of keys in HashMap, so this test is not deterministic.
-  // When this test fails, it is because the order of keys in the HashMap is
-  // different and everything else is the same. Proven by the fact that after
-  // multiple test runs that failed only because of the order of keys
-  // (observed), one was successful when the order was the same.
-
-  // #[test]
-  // fn json_stringify_object() {
-  //   let result =
-  // JSON::stringify(JsonValue::Object(HashMap::from([("a".to_string(),
-  // JsonValue::Null), ("b".to_string(), JsonValue::Boolean(true)),
-  // ("c".to_string(), JsonValue::Number(123.0)), ("d".to_string(),
-  // JsonValue::String("hello".to_string()))])), 0);   assert_eq!(result,
-  // "{\"a\":null,\"b\":true,\"c\":123,\"d\":\"hello\"}");   let result =
-  // JSON::stringify(JsonValue::Object(HashMap::from([("a".to_string(),
-  // JsonValue::Number(42.0)), ("b".to_string(),
-  // JsonValue::Array(vec![JsonValue::Boolean(true)])), ("c".to_string(),
-  // JsonValue::String("a".to_string()))])), 0);   assert_eq!(result,
-  // "{\"a\":42,\"b\":[true],\"c\":\"a\"}"); }
+  #[test]
+  fn json_value_at() {
+    let value = json!(["a", "b", "c"]);
+    assert_eq!(value.at(0), Some(&json!("a")));
+    assert_eq!(value.at(2), Some(&json!("c")));
+    assert_eq!(value.at(-1), Some(&json!("c")));
+    assert_eq!(value.at(-3), Some(&json!("a")));
+    assert_eq!(value.at(3), None);
+    assert_eq!(value.at(-4), None);
+    assert_eq!(value.at(isize::MIN), None);
+    assert_eq!(json!([]).at(-1), None);
+    assert_eq!(json!({"0": 1}).at(0), None);
+  }
+
+  #[test]
+  fn json_value_pointer() {
+    let value = JSON::parse(r#"{"foo":["bar",{"baz":1}],"a/b":2,"m~n":3,"":4}"#).unwrap();
+    assert_eq!(value.pointer(""), Some(&value));
+    assert_eq!(
+      value.pointer("/foo/0"),
+      Some(&JsonValue::String("bar".to_string()))
+    );
+    assert_eq!(value.pointer("/foo/1/baz"), Some(&JsonValue::Number(1.0)));
+    assert_eq!(value.pointer("/a~1b"), Some(&JsonValue::Number(2.0)));
+    assert_eq!(value.pointer("/m~0n"), Some(&JsonValue::Number(3.0)));
+    assert_eq!(value.pointer("/"), Some(&JsonValue::Number(4.0)));
+    assert_eq!(value.pointer("/foo/2"), None);
+    assert_eq!(value.pointer("/foo/01"), None);
+    assert_eq!(value.pointer("/foo/0/x"), None);
+    assert_eq!(value.pointer("/missing"), None);
+    assert_eq!(value.pointer("foo"), None);
+  }
+
+  #[test]
+  fn json_value_query() {
+    let store = json!({"store": {
+      "book": [
+        {"author": "Rees", "price": 8.95},
+        {"author": "Waugh", "price": 12.99},
+        {"author": "Tolkien", "price": 22.99, "isbn": "0-395"}
+      ],
+      "bicycle": {"color": "red", "price": 19.95}
+    }});
+    let query = |path| store.query(path).unwrap();
+    assert_eq!(query("$"), [&store]);
+    assert_eq!(query("$.store.bicycle.color"), [&json!("red")]);
+    assert_eq!(query("$['store'][\"bicycle\"]['color']"), [&json!("red")]);
+    assert_eq!(query("$.store.book[1].author"), [&json!("Waugh")]);
+    assert_eq!(query("$.store.book[-1].author"), [&json!("Tolkien")]);
+    assert_eq!(query("$.store.book[*].author"), [
+      &json!("Rees"),
+      &json!("Waugh"),
+      &json!("Tolkien")
+    ]);
+    assert_eq!(query("$.store.bicycle.*"), [&json!("red"), &json!(19.95)]);
+    assert_eq!(query("$..price"), [
+      &json!(8.95),
+      &json!(12.99),
+      &json!(22.99),
+      &json!(19.95)
+    ]);
+    assert_eq!(query("$..book[2].isbn"), [&json!("0-395")]);
+    assert_eq!(query("$..*").len(), 15);
+    assert!(query("$.store.book[3]").is_empty());
+    assert!(query("$.missing..price").is_empty());
+
+    for (path, index) in [
+      ("store", 0),
+      ("$.store[", 7),
+      ("$.", 2),
+      ("$[x]", 2),
+      ("$store", 1),
+    ] {
+      let error = store.query(path).unwrap_err();
+      assert_eq!(error.index, index, "{}", path);
+      assert!(error.message.starts_with("Invalid path"));
+    }
+  }
+
+  #[test]
+  fn json_value_pointer_mut() {
+    let mut value = json!({"a": {"b": [1, {"c": "old"}]}, "d/e": 0});
+    *value.pointer_mut("/a/b/1/c").unwrap() = json!("new");
+    value.pointer_mut("/a/b").unwrap().push(json!(3));
+    *value.pointer_mut("/d~1e").unwrap() = json!(true);
+    assert_eq!(
+      value,
+      json!({"a": {"b": [1, {"c": "new"}, 3]}, "d/e": true})
+    );
+    assert!(value.pointer_mut("/a/x").is_none());
+    assert!(value.pointer_mut("a").is_none());
+    *value.pointer_mut("").unwrap() = json!(null);
+    assert!(value.is_null());
+
+    let mut value = json!({"list": [0, 1]});
+    *value.get_mut("list").unwrap().get_index_mut(1).unwrap() = json!(5);
+    assert_eq!(value, json!({"list": [0, 5]}));
+    assert!(value.get_mut("missing").is_none());
+    assert!(value.get_index_mut(0).is_none());
+    assert!(value["list"].get_index_mut(2).is_none());
+  }
+
+  #[test]
+  fn json_value_merge() {
+    let mut value = json!({"a": "b", "c": {"d": "e", "f": "g"}, "h": [1, 2]});
+    value.merge(&json!({"a": "z", "c": {"f": null, "x": {"y": null, "w": 1}}, "h": [3]}));
+    assert_eq!(
+      value,
+      json!({"a": "z", "c": {"d": "e", "x": {"w": 1}}, "h": [3]})
+    );
+    let mut value = json!({"a": [{"b": "c"}]});
+    value.merge(&json!({"a": 1, "n": null}));
+    assert_eq!(value, json!({"a": 1}));
+    let mut value = json!([1, 2]);
+    value.merge(&json!({"a": {"b": null}}));
+    assert_eq!(value, json!({"a": {}}));
+    let mut value = json!({"a": 1});
+    value.merge(&json!("replaced"));
+    assert_eq!(value, json!("replaced"));
+    let mut value = json!({"a": 1});
+    value.merge(&json!(null));
+    assert_eq!(value, JsonValue::Null);
+  }
+
+  #[test]
+  fn json_value_schema() {
+    let schema = Schema::Object(HashMap::from([
+      ("name".to_string(), Schema::String),
+      (
+        "port".to_string(),
+        Schema::Optional(Box::new(Schema::Integer)),
+      ),
+      (
+        "tags".to_string(),
+        Schema::ArrayOf(Box::new(Schema::String)),
+      ),
+      ("extra".to_string(), Schema::Optional(Box::new(Schema::Any))),
+    ]));
+    assert!(schema
+      .validate(&json!({"name": "a", "tags": ["b", "c"], "other": 1}))
+      .is_ok());
+    assert!(schema
+      .validate(&json!({"name": "a", "tags": [], "port": 80.0}))
+      .is_ok());
+
+    let document = json!({"port": 1.5, "tags": ["b", 2, null], "a/b": 1});
+    let errors = schema.validate(&document).unwrap_err();
+    let messages: Vec<_> = errors.iter().map(|error| error.message.as_str()).collect();
+    assert_eq!(messages, [
+      "Missing key at '/name'",
+      "Expected an integer at '/port', found a number",
+      "Expected a string at '/tags/1', found a number",
+      "Expected a string at '/tags/2', found null",
+    ]);
+    let errors = schema.validate(&json!([])).unwrap_err();
+    assert_eq!(
+      errors[0].message,
+      "Expected an object at '', found an array"
+    );
+  }
+
+  #[test]
+  fn json_value_approx_eq() {
+    let a = json!({"total": (0.1 + 0.2), "items": [1, 2.0000001, {"x": 5}]});
+    let b = JSON::parse(r#"{"items":[1.0,2,{"x":5}],"total":0.3}"#).unwrap();
+    assert_ne!(a, b);
+    assert!(a.approx_eq(&b, 1e-6));
+    assert!(b.approx_eq(&a, 1e-6));
+    assert!(!a.approx_eq(&b, 1e-9));
+    assert!(!a.approx_eq(&json!({"total": 0.3}), 1e-6));
+    assert!(!json!([1, 2]).approx_eq(&json!([1, 2, 3]), 1.0));
+    assert!(!json!("1").approx_eq(&json!(1), 1.0));
+    assert!(json!([f64::INFINITY, null]).approx_eq(&json!([f64::INFINITY, null]), 0.0));
+  }
+
+  #[test]
+  fn json_value_from_iterator() {
+    let array: JsonValue = vec![1, 2, 3].into_iter().map(JsonValue::from).collect();
+    assert_eq!(array, json!([1, 2, 3]));
+    let empty: JsonValue = std::iter::empty::<JsonValue>().collect();
+    assert_eq!(empty, json!([]));
+    let object: JsonValue = ["a", "b", "a"]
+      .iter()
+      .enumerate()
+      .map(|(i, key)| (key.to_string(), JsonValue::from(i as i64)))
+      .collect();
+    assert_eq!(object, json!({"a": 2, "b": 1}));
+    assert_eq!(object.entries().next().map(|(k, _)| k.as_str()), Some("a"));
+  }
+
+  #[test]
+  fn json_value_into_iterator() {
+    let mut value = json!([1, 2, 3]);
+    for item in &mut value {
+      *item = JsonValue::Integer(item.as_i64().unwrap() * 10);
+    }
+    let mut sum = 0;
+    for item in &value {
+      sum += item.as_i64().unwrap();
+    }
+    assert_eq!(sum, 60);
+    let owned: Vec<JsonValue> = value.into_iter().collect();
+    assert_eq!(owned, vec![json!(10), json!(20), json!(30)]);
+
+    let mut object = json!({"a": 1});
+    assert_eq!((&object).into_iter().count(), 0);
+    assert_eq!((&mut object).into_iter().count(), 0);
+    assert_eq!(object.into_iter().count(), 0);
+    assert_eq!(json!("abc").into_iter().count(), 0);
+  }
+
+  #[test]
+  fn json_value_ordering() {
+    let mut values = vec![
+      json!({"b": 1}),
+      json!("b"),
+      json!([1, 2]),
+      json!(2.5),
+      json!(true),
+      json!(null),
+      json!({"a": 2}),
+      json!(-1),
+      json!("a"),
+      json!([1]),
+      json!(false),
+      json!(3),
+      json!({"a": 1, "b": 0}),
+    ];
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(
+      JsonValue::Array(values),
+      json!([null, false, true, -1, 2.5, 3, "a", "b", [1], [1, 2], {"a": 1, "b": 0}, {"a": 2}, {"b": 1}])
+    );
+    assert!(json!(1) < json!(1.5));
+    assert_eq!(
+      json!(1).partial_cmp(&json!(1.0)),
+      Some(std::cmp::Ordering::Equal)
+    );
+    assert_eq!(
+      json!({"a": 1, "b": 2}).partial_cmp(&json!({"b": 2, "a": 1})),
+      Some(std::cmp::Ordering::Equal)
+    );
+
+    let nan = JsonValue::Number(f64::NAN);
+    assert_eq!(nan.partial_cmp(&json!(1)), None);
+    assert_eq!(json!([nan.clone()]).partial_cmp(&json!([1])), None);
+    assert!(nan < json!("a"));
+    let mut values = [nan.clone(), json!(2), json!("x"), json!(1)];
+    values.sort_by(JsonValue::total_cmp);
+    assert!(values[0] == json!(1) && values[1] == json!(2));
+    assert!(values[2].as_f64().unwrap().is_nan() && values[3] == json!("x"));
+
+    let big = 1i64 << 53;
+    let (a, b, c) = (json!(big), json!(big as f64), json!(big + 1));
+    assert_eq!(a.total_cmp(&b), std::cmp::Ordering::Equal);
+    assert_eq!(b.total_cmp(&c), std::cmp::Ordering::Less);
+    assert_eq!(c.total_cmp(&b), std::cmp::Ordering::Greater);
+    assert!(json!(i64::MAX) < json!(9_223_372_036_854_775_808.0));
+    assert!(json!(i64::MIN) <= json!(-9_223_372_036_854_775_808.0));
+    assert!(json!(i64::MIN) > json!(-1e19));
+    assert!(json!(-1) < json!(-0.5) && json!(0) > json!(-0.5));
+    assert!(json!(f64::INFINITY) > json!(i64::MAX));
+    let mut values: Vec<_> = (0..40)
+      .map(|i| match i % 3 {
+        0 => json!(big + i),
+        1 => json!((big + i) as f64),
+        _ => json!(big - i),
+      })
+      .collect();
+    values.sort_by(JsonValue::total_cmp);
+    assert!(values.windows(2).all(|w| w[0].total_cmp(&w[1]).is_le()));
+  }
+
+  #[test]
+  fn json_value_flatten() {
+    let value = json!({
+      "user": {"name": "Ann", "address": {"city": "Oslo", "zip": null}},
+      "items": [10, {"id": true}, []],
+      "empty": {}
+    });
+    let flat = value.flatten();
+    let expected: HashMap<String, JsonValue> = [
+      ("user.name", json!("Ann")),
+      ("user.address.city", json!("Oslo")),
+      ("user.address.zip", json!(null)),
+      ("items.0", json!(10)),
+      ("items.1.id", json!(true)),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v))
+    .collect();
+    assert_eq!(flat, expected);
+    let flat = value.flatten_with("__");
+    assert_eq!(flat["user__address__city"], json!("Oslo"));
+    assert_eq!(flat["items__1__id"], json!(true));
+    assert_eq!(
+      json!(1).flatten(),
+      HashMap::from([(String::new(), json!(1))])
+    );
+  }
+
+  #[test]
+  fn json_value_unflatten() {
+    let flat = |pairs: &[(&str, JsonValue)]| -> HashMap<String, JsonValue> {
+      pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.clone()))
+        .collect()
+    };
+    let map = flat(&[
+      ("user.name", json!("Ann")),
+      ("user.address.city", json!("Oslo")),
+      ("user.address.zip", json!(null)),
+      ("enabled", json!(true)),
+    ]);
+    assert_eq!(
+      JsonValue::unflatten(&map).unwrap(),
+      json!({"enabled": true, "user": {"address": {"city": "Oslo", "zip": null}, "name": "Ann"}})
+    );
+
+    let map = flat(&[
+      ("items.10", json!("k")),
+      ("items.2.id", json!(2)),
+      ("items.0", json!("a")),
+      ("items.2.tags.0", json!("x")),
+    ]);
+    let map = (1..10)
+      .filter(|i| *i != 2)
+      .map(|i| (format!("items.{}", i), json!(i)))
+      .chain(map)
+      .collect();
+    let value = JsonValue::unflatten(&map).unwrap();
+    assert_eq!(value["items"].len(), 11);
+    assert_eq!(value["items"][0], json!("a"));
+    assert_eq!(value["items"][1], json!(1));
+    assert_eq!(value["items"][2], json!({"id": 2, "tags": ["x"]}));
+    assert_eq!(value["items"][10], json!("k"));
+    let map = flat(&[("0_a", json!(1)), ("1", json!(2))]);
+    assert_eq!(
+      JsonValue::unflatten_with(&map, "_").unwrap(),
+      json!([{"a": 1}, 2])
+    );
+
+    let value = json!({"a": [1, {"b": [true, null]}], "c": {"d": "e"}});
+    assert_eq!(JsonValue::unflatten(&value.flatten()).unwrap(), value);
+    assert_eq!(JsonValue::unflatten(&json!(5).flatten()).unwrap(), json!(5));
+    assert_eq!(JsonValue::unflatten(&HashMap::new()).unwrap(), json!({}));
+
+    for (pairs, path) in [
+      (vec![("a", json!(1)), ("a.b", json!(2))], "a.b"),
+      (vec![("a.0", json!(1)), ("a.b", json!(2))], "a.b"),
+      (vec![("a.0", json!(1)), ("a.0.b", json!(2))], "a.0.b"),
+      (vec![("a.0", json!(1)), ("a.01", json!(2))], "a.01"),
+      (vec![("", json!(1)), ("a", json!(2))], ""),
+    ] {
+      let error = JsonValue::unflatten(&flat(&pairs)).unwrap_err();
+      assert_eq!(
+        error.message,
+        format!("Path '{}' conflicts with another path", path)
+      );
+    }
+    for (pairs, path) in [
+      (vec![("a.0", json!(1)), ("a.2", json!(2))], "a.2"),
+      (vec![("a.1.b", json!(1))], "a.1.b"),
+      (vec![("a.99999999999999", json!(null))], "a.99999999999999"),
+    ] {
+      let error = JsonValue::unflatten(&flat(&pairs)).unwrap_err();
+      assert_eq!(
+        error.message,
+        format!("Path '{}' skips an array index", path)
+      );
+    }
+  }
+
+  #[test]
+  fn json_value_sorted() {
+    let a = JSON::parse(r#"{"b":{"y":[{"d":1,"c":2}],"x":null},"a":"s"}"#).unwrap();
+    let b = JSON::parse(r#"{"a":"s","b":{"x":null,"y":[{"c":2,"d":1}]}}"#).unwrap();
+    assert_ne!(
+      JSON::stringify(&a, PrettyMode::Compact),
+      JSON::stringify(&b, PrettyMode::Compact)
+    );
+    let canonical = JSON::stringify(&a.sorted(), PrettyMode::Compact);
+    assert_eq!(canonical, JSON::stringify(&b.sorted(), PrettyMode::Compact));
+    assert_eq!(canonical, r#"{"a":"s","b":{"x":null,"y":[{"c":2,"d":1}]}}"#);
+    assert_eq!(a.sorted(), a);
+    assert_eq!(json!([3, "x"]).sorted(), json!([3, "x"]));
+  }
+
+  #[test]
+  fn json_value_apply_patch() {
+    let mut value = json!({"a": {"b": [1, 2]}, "c": "d"});
+    value
+      .apply_patch(&json!([
+        {"op": "add", "path": "/a/b/1", "value": 9},
+        {"op": "add", "path": "/a/b/-", "value": 3},
+        {"op": "add", "path": "/e", "value": {"f": true}},
+        {"op": "remove", "path": "/c"},
+        {"op": "replace", "path": "/a/b/0", "value": "one"},
+        {"op": "move", "from": "/e/f", "path": "/g"},
+        {"op": "copy", "from": "/a/b", "path": "/h"},
+        {"op": "test", "path": "/h/1", "value": 9}
+      ]))
+      .unwrap();
+    assert_eq!(
+      value,
+      json!({"a": {"b": ["one", 9, 2, 3]}, "e": {}, "g": true, "h": ["one", 9, 2, 3]})
+    );
+    value
+      .apply_patch(&json!([{"op": "add", "path": "", "value": [1]}]))
+      .unwrap();
+    assert_eq!(value, json!([1]));
+  }
+
+  #[test]
+  fn json_value_apply_patch_errors() {
+    let original = json!({"a": [1], "b": {"c": null}});
+    let cases = [
+      (
+        json!([{"op": "test", "path": "/a/0", "value": 2}]),
+        "Test failed at '/a/0'",
+        0,
+      ),
+      (
+        json!([{"op": "remove", "path": "/b/c"}, {"op": "remove", "path": "/b/c"}]),
+        "Path '/b/c' does not exist",
+        1,
+      ),
+      (
+        json!([{"op": "replace", "path": "/x", "value": 1}]),
+        "Path '/x' does not exist",
+        0,
+      ),
+      (
+        json!([{"op": "add", "path": "/a/5", "value": 1}]),
+        "Invalid array index in '/a/5'",
+        0,
+      ),
+      (
+        json!([{"op": "move", "from": "/b", "path": "/b/d"}]),
+        "Cannot move '/b' into itself",
+        0,
+      ),
+      (json!([{"op": "copy", "path": "/x"}]), "Missing 'from'", 0),
+      (
+        json!([{"op": "nope", "path": ""}]),
+        "Unknown operation 'nope'",
+        0,
+      ),
+      (
+        json!({"op": "add"}),
+        "Patch must be an array of operations",
+        0,
+      ),
+    ];
+    for (ops, message, index) in cases {
+      let mut value = original.clone();
+      let error = value.apply_patch(&ops).unwrap_err();
+      assert_eq!((error.message.as_str(), error.index), (message, index));
+      assert_eq!(value, original);
+    }
+  }
+
+  #[test]
+  fn json_value_diff() {
+    let old = json!({"a": 1, "b": {"c": [1, 2], "d": "x"}, "e": null});
+    let new = json!({"a": 2, "b": {"c": [1], "d": "x", "f/g": true}, "e": null});
+    assert_eq!(
+      old.diff(&new),
+      json!({
+        "/a": {"old": 1, "new": 2},
+        "/b/c/1": {"old": 2},
+        "/b/f~1g": {"new": true},
+      })
+    );
+    assert_eq!(
+      json!({"a": 1}).diff(&json!({"a": 1, "b": [1]})),
+      json!({"/b": {"new": [1]}})
+    );
+    assert_eq!(
+      json!({"a": 1, "b": 2}).diff(&json!({"b": 2})),
+      json!({"/a": {"old": 1}})
+    );
+    assert_eq!(
+      json!({"a": [1]}).diff(&json!({"a": {"0": 1}})),
+      json!({"/a": {"old": [1], "new": {"0": 1}}})
+    );
+    assert_eq!(
+      json!(1).diff(&json!("1")),
+      json!({"": {"old": 1, "new": "1"}})
+    );
+    assert_eq!(old.diff(&old), json!({}));
+  }
+
+  #[test]
+  fn json_value_structural_hash() {
+    use std::{collections::hash_map::DefaultHasher, hash::Hasher};
+    let hash = |value: &JsonValue| {
+      let mut hasher = DefaultHasher::new();
+      value.structural_hash(&mut hasher);
+      hasher.finish()
+    };
+    let a = JSON::parse(r#"{"a": 1, "b": {"c": [true, null], "d": "x"}}"#).unwrap();
+    let b = JSON::parse(r#"{"b": {"d": "x", "c": [true, null]}, "a": 1.0}"#).unwrap();
+    assert_eq!(a, b);
+    assert_eq!(hash(&a), hash(&b));
+    assert_eq!(hash(&json!(0.0)), hash(&json!(-0.0)));
+    assert_eq!(hash(&json!(f64::NAN)), hash(&json!(-f64::NAN)));
+    assert_eq!(
+      hash(&json!(2)),
+      hash(&JsonValue::RawNumber("2.0".to_string()))
+    );
+    assert_ne!(hash(&json!([1, 2])), hash(&json!([2, 1])));
+    assert_ne!(hash(&json!({"a": "b"})), hash(&json!({"b": "a"})));
+    assert_ne!(hash(&json!("1")), hash(&json!(1)));
+  }
+
+  #[test]
+  fn json_value_len() {
+    assert_eq!(json!([1, 2, 3]).len(), 3);
+    assert_eq!(json!({"a": 1, "b": 2}).len(), 2);
+    assert_eq!(json!("é").len(), 2);
+    assert_eq!(json!(null).len(), 0);
+    assert_eq!(json!(true).len(), 0);
+    assert_eq!(json!(1.5).len(), 0);
+    assert!(json!([]).is_empty());
+    assert!(json!({}).is_empty());
+    assert!(json!("").is_empty());
+    assert!(json!(7).is_empty());
+    assert!(!json!([null]).is_empty());
+  }
+
+  #[test]
+  fn json_value_iterators() {
+    let mut value = json!([1, 2, 3]);
+    let sum: i64 = value.array_iter().filter_map(JsonValue::as_i64).sum();
+    assert_eq!(sum, 6);
+    for v in value.array_iter_mut() {
+      *v = JsonValue::from(v.as_i64().unwrap() * 10);
+    }
+    assert_eq!(value, json!([10, 20, 30]));
+    assert_eq!(value.entries().count(), 0);
+
+    let mut value = json!({"b": 1, "a": 2});
+    let keys: Vec<_> = value.entries().map(|(k, _)| k.as_str()).collect();
+    assert_eq!(keys, ["b", "a"]);
+    for (_, v) in value.entries_mut() {
+      *v = JsonValue::Null;
+    }
+    assert_eq!(value, json!({"a": null, "b": null}));
+    assert_eq!(value.array_iter().count(), 0);
+    assert_eq!(json!("s").entries_mut().count(), 0);
+  }
+
+  #[test]
+  fn json_value_insert_remove_push() {
+    let mut value = json!({});
+    assert_eq!(value.insert("a", json!([])), None);
+    assert_eq!(value.insert("b".to_string(), json!(1)), None);
+    assert_eq!(value.insert("b", json!(2)), Some(json!(1)));
+    value["a"].push(json!(true));
+    value["a"].push(json!(null));
+    assert_eq!(value.remove("b"), Some(json!(2)));
+    assert_eq!(value.remove("b"), None);
+    assert_eq!(value, json!({"a": [true, null]}));
+    assert_eq!(value["a"].remove("a"), None);
+  }
+
+  #[test]
+  fn json_value_entry() {
+    let records = json!([
+      {"team": "a", "name": "x"},
+      {"team": "b", "name": "y"},
+      {"team": "a", "name": "z"},
+    ]);
+    let mut groups = json!({});
+    for record in &records {
+      let team = record["team"].as_str().unwrap();
+      groups
+        .entry_or_insert_with(team, || json!([]))
+        .push(record["name"].clone());
+      let count = groups.entry("count");
+      *count = json!(count.as_i64().unwrap_or(0) + 1);
+    }
+    assert_eq!(groups, json!({"a": ["x", "z"], "count": 3, "b": ["y"]}));
+    assert!(json!({}).entry("k").is_null());
+    let mut value = json!({"k": 1});
+    assert_eq!(
+      *value.entry_or_insert_with("k", || unreachable!()),
+      json!(1)
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "not an object")]
+  fn json_value_entry_mismatch() { json!("s").entry("a"); }
+
+  #[test]
+  fn json_value_take_replace() {
+    let mut value = json!({"a": {"big": [1, 2, 3]}, "b": 1});
+    let taken = value["a"].take();
+    assert_eq!(taken, json!({"big": [1, 2, 3]}));
+    assert_eq!(value, json!({"a": null, "b": 1}));
+    for (_, v) in value.entries_mut() {
+      let old = v.replace(json!("x"));
+      assert!(!old.is_string());
+    }
+    assert_eq!(value, json!({"a": "x", "b": "x"}));
+    assert_eq!(JsonValue::default(), JsonValue::Null);
+  }
+
+  #[test]
+  #[should_panic(expected = "not an object")]
+  fn json_value_insert_mismatch() { json!([]).insert("a", JsonValue::Null); }
+
+  #[test]
+  fn json_value_index_mut() {
+    let mut value = json!({"users": [{"name": "a"}, {"name": "b"}]});
+    value["users"][0]["name"] = JsonValue::from("z");
+    value["users"][1]["age"] = JsonValue::from(3);
+    assert_eq!(
+      value,
+      json!({"users": [{"name": "z"}, {"name": "b", "age": 3}]})
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "not an array")]
+  fn json_value_index_mut_mismatch() { json!({"a": 1})[0] = JsonValue::Null; }
+
+  #[test]
+  fn json_value_as() {
+    let value = JSON::parse(r#"{"s":"a","n":1.5,"b":true,"a":[null],"o":{}}"#).unwrap();
+    assert_eq!(value["s"].as_str(), Some("a"));
+    assert_eq!(value["n"].as_f64(), Some(1.5));
+    assert_eq!(value["b"].as_bool(), Some(true));
+    assert_eq!(value["a"].as_array(), Some(&vec![JsonValue::Null]));
+    assert_eq!(value["o"].as_object(), Some(&JsonMap::new()));
+    assert_eq!(value["s"].as_f64(), None);
+    assert_eq!(value["n"].as_str(), None);
+    assert_eq!(value["b"].as_array(), None);
+    assert_eq!(value["a"].as_object(), None);
+    assert_eq!(value["o"].as_bool(), None);
+  }
+
+  #[test]
+  fn json_value_is() {
+    let values = [
+      JsonValue::Null,
+      JsonValue::Boolean(false),
+      JsonValue::Number(0.0),
+      JsonValue::String(String::new()),
+      JsonValue::Array(vec![]),
+      JsonValue::Object(JsonMap::new()),
+    ];
+    for (i, value) in values.iter().enumerate() {
+      assert_eq!(value.is_null(), i == 0);
+      assert_eq!(value.is_boolean(), i == 1);
+      assert_eq!(value.is_number(), i == 2);
+      assert_eq!(value.is_string(), i == 3);
+      assert_eq!(value.is_array(), i == 4);
+      assert_eq!(value.is_object(), i == 5);
+    }
+  }
+
+  #[test]
+  fn json_value_from() {
+    assert_eq!(JsonValue::from(true), JsonValue::Boolean(true));
+    assert_eq!(JsonValue::from(1.5), JsonValue::Number(1.5));
+    assert_eq!(JsonValue::from(42), JsonValue::Number(42.0));
+    assert_eq!(JsonValue::from(-7i64), JsonValue::Number(-7.0));
+    assert_eq!(JsonValue::from("a"), JsonValue::String("a".to_string()));
+    assert_eq!(
+      JsonValue::from("b".to_string()),
+      JsonValue::String("b".to_string())
+    );
+    assert_eq!(
+      JsonValue::from(vec![JsonValue::Null]),
+      JsonValue::Array(vec![JsonValue::Null])
+    );
+    let obj = HashMap::from([("a".to_string(), JsonValue::Null)]);
+    assert_eq!(JsonValue::from(obj.clone()), JsonValue::Object(obj.into()));
+    assert_eq!(
+      JsonValue::from(Some("a")),
+      JsonValue::String("a".to_string())
+    );
+    assert_eq!(JsonValue::from(None::<f64>), JsonValue::Null);
+  }
+
+  #[test]
+  fn json_macro() {
+    assert_eq!(json!(null), JsonValue::Null);
+    assert_eq!(json!([]), JsonValue::Array(vec![]));
+    assert_eq!(json!({}), JsonValue::Object(JsonMap::new()));
+    assert_eq!(
+      json!({"a": 1, "b": [true, null]}),
+      JSON::parse(r#"{"a":1,"b":[true,null]}"#).unwrap()
+    );
+    let x = 2.5;
+    let key = "k";
+    assert_eq!(
+      json!({"x": x, (key): -x, "nested": {"s": "t", "arr": [[], {}, -1, x * 2.0,]},}),
+      JSON::parse(r#"{"x":2.5,"k":-2.5,"nested":{"s":"t","arr":[[],{},-1,5]}}"#).unwrap()
+    );
+  }
+
+  // --------------------------------
+
+  #[test]
+  fn json_stringify_null() {
+    let result = JSON::stringify(&JsonValue::Null, PrettyMode::Compact);
+    assert_eq!(result, "null");
+  }
+
+  #[test]
+  fn json_stringify_boolean() {
+    let result = JSON::stringify(&JsonValue::Boolean(true), PrettyMode::Compact);
+    assert_eq!(result, "true");
+    let result = JSON::stringify(&JsonValue::Boolean(false), PrettyMode::Compact);
+    assert_eq!(result, "false");
+  }
+
+  #[test]
+  fn json_stringify_number() {
+    let result = JSON::stringify(&JsonValue::Number(123.0), PrettyMode::Compact);
+    assert_eq!(result, "123");
+    let result = JSON::stringify(&JsonValue::Number(-123.456), PrettyMode::Compact);
+    assert_eq!(result, "-123.456");
+    let cases = [
+      (1e21, "1e+21"),
+      (1.5e300, "1.5e+300"),
+      (1e20, "100000000000000000000"),
+      (0.1, "0.1"),
+      (1e-7, "1e-7"),
+      (1.25e-6, "0.00000125"),
+      (-0.0, "-0"),
+      (9007199254740993.0, "9007199254740992"),
+      (f64::MAX, "1.7976931348623157e+308"),
+      (f64::MIN_POSITIVE, "2.2250738585072014e-308"),
+    ];
+    for (n, expected) in cases {
+      let result = JSON::stringify(&JsonValue::Number(n), PrettyMode::Compact);
+      assert_eq!(result, expected);
+      let parsed = JSON::parse(&result).unwrap().as_f64().unwrap();
+      assert_eq!(parsed.to_bits(), n.to_bits());
+    }
+  }
+
+  #[test]
+  fn json_stringify_string() {
+    let result = JSON::stringify(&JsonValue::String("hello".to_string()), PrettyMode::Compact);
+    assert_eq!(result, "\"hello\"");
+    let result = JSON::stringify(
+      &JsonValue::String("hello\\ world\n".to_string()),
+      PrettyMode::Compact,
+    );
+    assert_eq!(result, "\"hello\\\\ world\\n\"");
+  }
+
+  #[test]
+  fn json_stringify_string_escapes() {
+    let value = json!("a\\b\"c\x08\x0c\n\r\t\u{e9}/\x02\u{1f600}");
+    let result = JSON::stringify(&value, PrettyMode::Compact);
+    assert_eq!(
+      result,
+      "\"a\\\\b\\\"c\\b\\f\\n\\r\\t\u{e9}/\\u0002\u{1f600}\""
+    );
+    assert_eq!(JSON::parse(result).unwrap(), value);
+    assert_eq!(JSON::stringify(&json!(""), PrettyMode::Compact), "\"\"");
+  }
+
+  #[test]
+  fn json_stringify_object_keys() {
+    let value = json!({"a\"b": 1, "c\\d\ne": [], "": null});
+    let result = JSON::stringify(&value, PrettyMode::Compact);
+    assert_eq!(result, r#"{"a\"b":1,"c\\d\ne":[],"":null}"#);
+    let parsed = JSON::parse(result).unwrap();
+    assert_eq!(parsed["a\"b"], json!(1));
+    assert_eq!(parsed, value);
+  }
+
+  #[test]
+  fn json_stringify_control_chars() {
+    let value = json!("a\x01b\x1f\x00\t\x7f");
+    let result = JSON::stringify(&value, PrettyMode::Compact);
+    assert_eq!(result, "\"a\\u0001b\\u001f\\u0000\\t\x7f\"");
+    assert_eq!(JSON::parse(result).unwrap(), value);
+  }
+
+  #[test]
+  fn json_stringify_escape_non_ascii() {
+    let value = json!({"caf\u{e9}": "\u{e9}\u{1f600}", "a": "plain"});
+    let options = StringifyOptions::new().escape_non_ascii(true);
+    let result = JSON::stringify_with(&value, &options);
+    assert_eq!(result, r#"{"caf\u00e9":"\u00e9\ud83d\ude00","a":"plain"}"#);
+    assert!(result.is_ascii());
+    assert_eq!(JSON::parse(result).unwrap(), value);
+    assert_eq!(
+      JSON::stringify(&value, PrettyMode::Compact),
+      "{\"caf\u{e9}\":\"\u{e9}\u{1f600}\",\"a\":\"plain\"}"
+    );
+  }
+
+  #[test]
+  fn json_stringify_slash() {
+    let input = r#"{"url":"http://example.com/a/b"}"#;
+    let value = JSON::parse(input).unwrap();
+    assert_eq!(JSON::stringify(&value, PrettyMode::Compact), input);
+    let options = StringifyOptions {
+      escape_slash: true,
+      ..Default::default()
+    };
+    let escaped = JSON::stringify_with(&value, &options);
+    assert_eq!(escaped, r#"{"url":"http:\/\/example.com\/a\/b"}"#);
+    assert_eq!(JSON::parse(escaped).unwrap(), value);
+  }
+
+  #[test]
+  fn json_stringify_array() {
+    let result = JSON::stringify(
+      &JsonValue::Array(vec![
+        JsonValue::Null,
+        JsonValue::Boolean(true),
+        JsonValue::Number(123.0),
+        JsonValue::String("hello".to_string()),
+      ]),
+      PrettyMode::Compact,
+    );
+    assert_eq!(result, "[null,true,123,\"hello\"]");
+    let result = JSON::stringify(
+      &JsonValue::Array(vec![
+        JsonValue::Number(42.0),
+        JsonValue::Array(vec![JsonValue::Boolean(true)]),
+        JsonValue::String("a".to_string()),
+      ]),
+      PrettyMode::Compact,
+    );
+    assert_eq!(result, "[42,[true],\"a\"]");
+  }
+
+  #[test]
+  fn json_stringify_pretty_mode() {
+    let value = json!([1, {"a": null}]);
+    assert_eq!(
+      JSON::stringify(&value, PrettyMode::Compact),
+      "[1,{\"a\":null}]"
+    );
+    assert_eq!(
+      JSON::stringify(&value, PrettyMode::Spaces),
+      "[1, {\"a\": null}]"
+    );
+    let nested = json!({"a": [[], {}, [null, true]], "b": {"c": {"d": "e, f"}}});
+    assert_eq!(
+      JSON::stringify(&nested, PrettyMode::Spaces),
+      r#"{"a": [[], {}, [null, true]], "b": {"c": {"d": "e, f"}}}"#
+    );
+    assert_eq!(
+      JSON::stringify(&value, PrettyMode::Indented),
+      "[\n  1,\n  {\n    \"a\": null\n  }\n]"
+    );
+    #[allow(deprecated)]
+    let levels = [0, 1, 2, 3].map(|level| JSON::stringify_level(&value, level));
+    assert_eq!(levels[0], JSON::stringify(&value, PrettyMode::Compact));
+    assert_eq!(levels[1], JSON::stringify(&value, PrettyMode::Spaces));
+    assert_eq!(levels[2], JSON::stringify(&value, PrettyMode::Indented));
+    assert_eq!(levels[3], levels[0]);
+  }
+
+  #[test]
+  fn json_stringify_indent() {
+    let value = json!({"a": [1, {"b": null}]});
+    let options = StringifyOptions {
+      pretty: PrettyMode::Indented,
+      indent: "\t".to_string(),
+      ..Default::default()
+    };
+    assert_eq!(
+      JSON::stringify_with(&value, &options),
+      "{\n\t\"a\": [\n\t\t1,\n\t\t{\n\t\t\t\"b\": null\n\t\t}\n\t]\n}"
+    );
+    let options = StringifyOptions {
+      indent: "    ".to_string(),
+      ..options
+    };
+    assert_eq!(
+      JSON::stringify_with(&json!([[1]]), &options),
+      "[\n    [\n        1\n    ]\n]"
+    );
+    let options = StringifyOptions {
+      indent: String::new(),
+      ..options
+    };
+    assert_eq!(
+      JSON::stringify_with(&value, &options),
+      "{\n\"a\": [\n1,\n{\n\"b\": null\n}\n]\n}"
+    );
+  }
+
+  #[test]
+  fn json_stringify_options_builder() {
+    let value = json!({"b": [1, {"c": null}], "a": true});
+    let options = StringifyOptions::new().sort_keys(true).colon_space(true);
+    assert_eq!(
+      JSON::stringify_with(&value, &options),
+      r#"{"a": true,"b": [1,{"c": null}]}"#
+    );
+    let options = StringifyOptions::new()
+      .pretty(PrettyMode::Indented)
+      .indent("\t")
+      .colon_space(false);
+    assert_eq!(
+      JSON::stringify_with(&value, &options),
+      "{\n\t\"b\":[\n\t\t1,\n\t\t{\n\t\t\t\"c\":null\n\t\t}\n\t],\n\t\"a\":true\n}"
+    );
+    let options = StringifyOptions::new().pretty(PrettyMode::Spaces);
+    assert_eq!(
+      JSON::stringify_with(&value, &options),
+      JSON::stringify(&value, PrettyMode::Spaces)
+    );
+    assert_eq!(StringifyOptions::new(), StringifyOptions::default());
+  }
+
+  #[test]
+  fn json_stringify_pretty_and_compact() {
+    let value = json!({"a": [1, {"b": null}], "c": "d"});
+    assert_eq!(
+      JSON::pretty(&value),
+      JSON::stringify(&value, PrettyMode::Indented)
+    );
+    assert_eq!(
+      JSON::compact(&value),
+      JSON::stringify(&value, PrettyMode::Compact)
+    );
+    assert_eq!(JSON::compact(&value), r#"{"a":[1,{"b":null}],"c":"d"}"#);
+    assert_eq!(JSON::pretty(&json!([1])), "[\n  1\n]");
+  }
+
+  #[test]
+  fn json_stringify_serde_pretty() {
+    let value = JSON::parse(
+      r#"{"name":"x","tags":["a","b/c"],"empty":[],"meta":{},"n":{"id":-3,"ok":true,"v":null}}"#,
+    )
+    .unwrap();
+    // The output of `serde_json::to_string_pretty` for the same document.
+    let expected = r#"{
+  "name": "x",
+  "tags": [
+    "a",
+    "b/c"
+  ],
+  "empty": [],
+  "meta": {},
+  "n": {
+    "id": -3,
+    "ok": true,
+    "v": null
+  }
+}"#;
+    let options = StringifyOptions::serde_pretty();
+    assert_eq!(JSON::stringify_with(&value, &options), expected);
+    assert_eq!(
+      JSON::stringify_with(&json!({"b": "\u{1f}", "a": [[]]}), &options.sort_keys(true)),
+      "{\n  \"a\": [\n    []\n  ],\n  \"b\": \"\\u001f\"\n}"
+    );
+
+    // What serde_json writes for each of these floats.
+    let value =
+      json!([1.0, -0.0, 0.5, 1e15, 1e16, 1e21, 1.5e300, 0.00001, 1e-6, -2.5e-7, 123.456, 7]);
+    let options = StringifyOptions::new().serde_floats(true);
+    assert_eq!(
+      JSON::stringify_with(&value, &options),
+      "[1.0,-0.0,0.5,1000000000000000.0,1e16,1e21,1.5e300,0.00001,1e-6,-2.5e-7,123.456,7]"
+    );
+    assert_eq!(
+      JSON::stringify_with(&json!([1.0]), &StringifyOptions::serde_pretty()),
+      "[\n  1.0\n]"
+    );
+  }
+
+  #[test]
+  fn json_stringify_trailing_newline() {
+    let value = json!({"a": [1, 2]});
+    let options = StringifyOptions::new().pretty(PrettyMode::Indented);
+    assert!(!JSON::stringify_with(&value, &options).ends_with('\n'));
+    let options = options.trailing_newline(true);
+    assert_eq!(
+      JSON::stringify_with(&value, &options),
+      "{\n  \"a\": [\n    1,\n    2\n  ]\n}\n"
+    );
+    let options = StringifyOptions::new().trailing_newline(true);
+    assert_eq!(JSON::stringify_with(&json!(null), &options), "null\n");
+  }
+
+  #[test]
+  fn json_stringify_deep_value() {
+    let depth = 50_000;
+    let mut built = json!(1);
+    for _ in 0..depth {
+      built = json!([{ "a": built }]);
+    }
+    let expected = format!("{}1{}", r#"[{"a":"#.repeat(depth), "}]".repeat(depth));
+    let options = ParseOptions {
+      max_depth: 2 * depth,
+      ..Default::default()
+    };
+    let parsed = JSON::parse_with(&expected, options).unwrap();
+    for value in [&built, &parsed] {
+      assert_eq!(value.to_string(), expected);
+      assert_eq!(JSON::stringify(value, PrettyMode::Compact), expected);
+      let mut output = String::new();
+      JSON::stringify_to(value, &mut output, PrettyMode::Compact).unwrap();
+      assert_eq!(output, expected);
+      assert_eq!(JSON::canonicalize(value), expected);
+      // `JSON::pretty` would indent the innermost lines by 200,000 spaces.
+      let options = StringifyOptions::new()
+        .pretty(PrettyMode::Indented)
+        .indent("");
+      let pretty = JSON::stringify_with(value, &options);
+      assert_eq!(pretty.lines().count(), 4 * depth + 1);
+    }
+  }
+
+  #[test]
+  fn json_stringify_nan_infinity() {
+    let value = json!([f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1.5]);
+    assert_eq!(
+      JSON::stringify(&value, PrettyMode::Compact),
+      "[null,null,null,1.5]"
+    );
+    let options = StringifyOptions {
+      allow_nan: true,
+      ..Default::default()
+    };
+    assert_eq!(
+      JSON::stringify_with(&value, &options),
+      "[NaN,Infinity,-Infinity,1.5]"
+    );
+  }
+
+  #[test]
+  fn json_display() {
+    assert_eq!(
+      format!("{}", JsonValue::Array(vec![JsonValue::Null])),
+      "[null]"
+    );
+    let value = JsonValue::Array(vec![
+      JsonValue::String("a\"b".to_string()),
+      JsonValue::Number(1.5),
+    ]);
+    assert_eq!(
+      value.to_string(),
+      JSON::stringify(&value, PrettyMode::Compact)
+    );
+  }
+
+  #[test]
+  fn json_stringify_object() {
+    let result = JSON::stringify_sorted(
+      &JsonValue::Object(JsonMap::from([
+        ("a".to_string(), JsonValue::Null),
+        ("b".to_string(), JsonValue::Boolean(true)),
+        ("c".to_string(), JsonValue::Number(123.0)),
+        ("d".to_string(), JsonValue::String("hello".to_string())),
+      ])),
+      PrettyMode::Compact,
+    );
+    assert_eq!(result, "{\"a\":null,\"b\":true,\"c\":123,\"d\":\"hello\"}");
+    let result = JSON::stringify_sorted(
+      &JsonValue::Object(JsonMap::from([
+        ("a".to_string(), JsonValue::Number(42.0)),
+        (
+          "b".to_string(),
+          JsonValue::Array(vec![JsonValue::Boolean(true)]),
+        ),
+        ("c".to_string(), JsonValue::String("a".to_string())),
+      ])),
+      PrettyMode::Compact,
+    );
+    assert_eq!(result, "{\"a\":42,\"b\":[true],\"c\":\"a\"}");
+    let result = JSON::stringify_sorted(
+      &json!({"b": {"z": 1, "y": 2}, "a": []}),
+      PrettyMode::Indented,
+    );
+    assert_eq!(
+      result,
+      "{\n  \"a\": [],\n  \"b\": {\n    \"y\": 2,\n    \"z\": 1\n  }\n}"
+    );
+  }
+
+  #[test]
+  fn json_stringify_to() {
+    let value = json!({"name": "a/b\n", "list": [1, 2.5, null, {"x": true}], "empty": {}});
+    for pretty in [
+      PrettyMode::Compact,
+      PrettyMode::Spaces,
+      PrettyMode::Indented,
+    ] {
+      let mut output = String::from("> ");
+      JSON::stringify_to(&value, &mut output, pretty).unwrap();
+      assert_eq!(output, format!("> {}", JSON::stringify(&value, pretty)));
+    }
+    assert_eq!(
+      value.to_string(),
+      JSON::stringify(&value, PrettyMode::Compact)
+    );
+  }
+
+  #[test]
+  fn json_stringify_canonicalize() {
+    let input = r#"{
+      "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+      "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+      "literals": [null, true, false]
+    }"#;
+    assert_eq!(
+      JSON::canonicalize(&JSON::parse(input).unwrap()),
+      "{\"literals\":[null,true,false],\"numbers\":[333333333.3333333,1e+30,4.5,0.002,1e-27],\"\
+       string\":\"\u{20ac}$\\u000f\\nA'B\\\"\\\\\\\\\\\"/\"}"
+    );
+    let input = r#"{
+      "€": "Euro Sign",
+      "\r": "Carriage Return",
+      "דּ": "Hebrew Letter Dalet With Dagesh",
+      "1": "One",
+      "😀": "Emoji: Grinning Face",
+      "\u0080": "Control",
+      "ö": "Latin Small Letter O With Diaeresis"
+    }"#;
+    let canonical = JSON::canonicalize(&JSON::parse(input).unwrap());
+    let keys: Vec<_> = JSON::parse(canonical)
+      .unwrap()
+      .as_object()
+      .unwrap()
+      .keys()
+      .cloned()
+      .collect();
+    assert_eq!(keys, [
+      "\r",
+      "1",
+      "\u{80}",
+      "\u{f6}",
+      "\u{20ac}",
+      "\u{1f600}",
+      "\u{fb33}"
+    ]);
+    assert_eq!(
+      JSON::canonicalize(&json!([-0.0, 9007199254740993_i64, {}, [], 1e21])),
+      "[0,9007199254740992,{},[],1e+21]"
+    );
+  }
+
+  #[test]
+  fn json_parse_deep_nesting() {
+    let depth = 50_000;
+    let json = "[".repeat(depth) + &"]".repeat(depth);
+    let options = ParseOptions {
+      max_depth: depth,
+      ..Default::default()
+    };
+    let value = JSON::parse_with(&json, options).unwrap();
+    let mut levels = 0;
+    let mut inner = &value;
+    while let Some(next) = inner.get_index(0) {
+      levels += 1;
+      inner = next;
+    }
+    assert_eq!(levels, depth - 1);
+    let copy = value.clone();
+    assert_eq!(copy, value);
+    assert_eq!(copy.partial_cmp(&value), Some(std::cmp::Ordering::Equal));
+    assert_eq!(value.total_cmp(&json!([])), std::cmp::Ordering::Greater);
+    use std::{collections::hash_map::DefaultHasher, hash::Hasher};
+    let hash = |value: &JsonValue| {
+      let mut hasher = DefaultHasher::new();
+      value.structural_hash(&mut hasher);
+      hasher.finish()
+    };
+    assert_eq!(hash(&copy), hash(&value));
+
+    let json = r#"{"a":"#.repeat(depth) + "1" + &"}".repeat(depth);
+    let value = JSON::parse_with(&json, options).unwrap();
+    let mut other = JSON::parse_with(json.replace('1', "2"), options).unwrap();
+    assert_ne!(value, other);
+    assert!(value < other);
+    other = value.clone();
+    assert_eq!(other, value);
+    assert_eq!(
+      JSON::parse(&json).unwrap_err().message,
+      "Maximum nesting depth exceeded"
+    );
+  }
+}
+
+#[cfg(test)]
+mod csv_tests {
+  use csv::*;
+
+  use super::*;
+
+  fn records(rows: &[&[&str]]) -> Vec<Vec<String>> {
+    rows
+      .iter()
+      .map(|row| row.iter().map(|field| field.to_string()).collect())
+      .collect()
+  }
+
+  #[test]
+  fn csv_parse() {
+    let result = CSV::parse("a,b,c\n1,2,3\n").unwrap();
+    assert_eq!(result, records(&[&["a", "b", "c"], &["1", "2", "3"]]));
+    let result = CSV::parse("a,,\r\n\r\nx").unwrap();
+    assert_eq!(result, records(&[&["a", "", ""], &[""], &["x"]]));
+    assert!(CSV::parse("").unwrap().is_empty());
+  }
+
+  #[test]
+  fn csv_parse_quoted() {
+    let result = CSV::parse("name,quote\n\"Smith, J\",\"He said \"\"hi\"\"\"\n").unwrap();
+    assert_eq!(
+      result,
+      records(&[&["name", "quote"], &["Smith, J", "He said \"hi\""]])
+    );
+    let result = CSV::parse("\"multi\nline\",\"\"\n2,3").unwrap();
+    assert_eq!(result, records(&[&["multi\nline", ""], &["2", "3"]]));
+  }
+
+  #[test]
+  fn csv_parse_delimiter() {
+    let options = CsvOptions { delimiter: ';' };
+    let result = CSV::parse_with("a;\"b;c\"\n1,5;2", options).unwrap();
+    assert_eq!(result, records(&[&["a", "b;c"], &["1,5", "2"]]));
+    let options = CsvOptions { delimiter: '\t' };
+    assert_eq!(
+      CSV::parse_with("a\tb", options).unwrap(),
+      records(&[&["a", "b"]])
+    );
+  }
+
+  #[test]
+  fn csv_parse_errors() {
+    let error = CSV::parse("a,b\n1,\"open\n").unwrap_err();
+    assert_eq!(
+      (
+        error.message.as_str(),
+        error.index,
+        error.line,
+        error.column
+      ),
+      ("Unterminated quoted field", 6, 2, 3)
+    );
+    assert_eq!(
+      error.to_string(),
+      "ERROR: Unterminated quoted field in CSV at line 2, column 3"
+    );
+    let error = CSV::parse("\"a\"b,c").unwrap_err();
+    assert_eq!(
+      (error.message.as_str(), error.index),
+      ("Unexpected 'b' after closing quote", 3)
+    );
+  }
+
+  #[test]
+  fn csv_parse_records() {
+    let result = CSV::parse_records("name,age\nAda,36\n\"Hopper, G\",85\n").unwrap();
+    assert_eq!(
+      result,
+      json!([{"name": "Ada", "age": "36"}, {"name": "Hopper, G", "age": "85"}])
+    );
+    let keys: Vec<_> = result[0].as_object().unwrap().keys().cloned().collect();
+    assert_eq!(keys, ["name", "age"]);
+    assert_eq!(CSV::parse_records("a,b").unwrap(), json!([]));
+    assert_eq!(CSV::parse_records("").unwrap(), json!([]));
+
+    let error = CSV::parse_records("a,b\n1,2\n3\n").unwrap_err();
+    assert_eq!(
+      (error.message.as_str(), error.index, error.line),
+      ("Row 3 has 1 fields, expected 2", 8, 3)
+    );
+  }
+
+  #[test]
+  fn csv_stringify() {
+    let value = json!([
+      {"name": "Smith, Jo", "quote": "say \"hi\"", "notes": "two\nlines"},
+      {"name": "Ann", "quote": "plain", "notes": ""}
+    ]);
+    let csv = CSV::stringify(&value).unwrap();
+    assert_eq!(
+      csv,
+      "name,quote,notes\n\"Smith, Jo\",\"say \"\"hi\"\"\",\"two\nlines\"\nAnn,plain,\n"
+    );
+    assert_eq!(CSV::parse_records(&csv).unwrap(), value);
+  }
+
+  #[test]
+  fn csv_stringify_ragged() {
+    let value = json!([
+      {"id": 1, "name": "a"},
+      {"id": 2, "extra": true},
+      {"name": "c", "tags": [1, 2], "id": null}
+    ]);
+    assert_eq!(
+      CSV::stringify(&value).unwrap(),
+      "id,name,extra,tags\n1,a,,\n2,,true,\n,c,,\"[1,2]\"\n"
+    );
+    assert_eq!(CSV::stringify(&json!([])).unwrap(), "");
+    assert_eq!(
+      CSV::stringify(&json!({"a": 1})).unwrap_err().message,
+      "Only an array of objects can be written as CSV"
+    );
+    let error = CSV::stringify(&json!([{"a": 1}, 2])).unwrap_err();
+    assert_eq!(
+      (error.message.as_str(), error.index),
+      ("Element 1 is not an object", 1)
+    );
+  }
+}
+
+#[cfg(test)]
+mod toml_tests {
+  use json::JsonValue;
+  use toml::*;
+
+  use super::*;
+
+  #[test]
+  fn toml_parse() {
+    let input = r#"
+# Server configuration
+title = "Example \u00e9"   # trailing comment
+path = 'C:\Users\n'
+"quoted key" = true
+server.port = 8_080
+
+[database]
+enabled = false
+ports = [ 8000, 8001,
+  8002, ]   # multi-line array
+ratio = -1.5e3
+limits = { cpu = 0.5, mem = "1G" }
+
+[database.replica]
+host = "10.0.0.2"
+
+[[products]]
+name = "Hammer"
+
+[[products]]
+name = "Nail"
+sku = 284758393
+"#;
+    let result = TOML::parse(input).unwrap();
+    assert_eq!(
+      result,
+      json!({
+        "title": "Example \u{e9}",
+        "path": "C:\\Users\\n",
+        "quoted key": true,
+        "server": {"port": 8080},
+        "database": {
+          "enabled": false,
+          "ports": [8000, 8001, 8002],
+          "ratio": -1500.0,
+          "limits": {"cpu": 0.5, "mem": "1G"},
+          "replica": {"host": "10.0.0.2"}
+        },
+        "products": [{"name": "Hammer"}, {"name": "Nail", "sku": 284758393}]
+      })
+    );
+    assert_eq!(result["database"]["ports"][0], JsonValue::Integer(8000));
+    assert_eq!(TOML::parse("").unwrap(), json!({}));
+  }
+
+  #[test]
+  fn toml_parse_array_of_tables() {
+    let input = "[[a]]\nx = 1\n[a.b]\ny = 2\n[[a]]\nx = 3\n[a.b]\ny = 4\n";
+    assert_eq!(
+      TOML::parse(input).unwrap(),
+      json!({"a": [{"x": 1, "b": {"y": 2}}, {"x": 3, "b": {"y": 4}}]})
+    );
+  }
+
+  #[test]
+  fn toml_stringify() {
+    let value = json!({
+      "title": "Say \"hi\"\n",
+      "database": {"ports": [8000, 8001], "ratio": 0.5, "replica": {}},
+      "owner name": {"first": "Tom"},
+      "products": [{"name": "Hammer", "size": {"w": 1}}, {"name": "Nail", "tags": [{"a": true}]}],
+      "limits": [1.0, 1e21, -2.5],
+      "grid": [[{"x": 1}], []]
+    });
+    let toml = TOML::stringify(&value).unwrap();
+    assert_eq!(
+      toml,
+      r#"title = "Say \"hi\"\n"
+limits = [1.0, 1e21, -2.5]
+grid = [[{ x = 1 }], []]
+
+[database]
+ports = [8000, 8001]
+ratio = 0.5
+
+[database.replica]
+
+["owner name"]
+first = "Tom"
+
+[[products]]
+name = "Hammer"
+
+[products.size]
+w = 1
+
+[[products]]
+name = "Nail"
+
+[[products.tags]]
+a = true
+"#
+    );
+    assert_eq!(TOML::parse(&toml).unwrap(), value);
+
+    for (value, message) in [
+      (json!([1]), "Only an object can be written as TOML"),
+      (json!({"a": {"b": null}}), "Null at 'a.b' has no TOML form"),
+      (
+        json!({"a": [1, {"b": 2}]}),
+        "Array at 'a' mixes tables and other values",
+      ),
+    ] {
+      assert_eq!(TOML::stringify(&value).unwrap_err().message, message);
+    }
+  }
+
+  #[test]
+  fn toml_parse_errors() {
+    for (input, message, line) in [
+      ("a = 1\na = 2", "Duplicate key 'a'", 2),
+      ("[t]\n[t]", "Table 't' is defined twice", 2),
+      ("a = 1\n[a]", "Key 'a' is not a table", 2),
+      ("a = \"open", "Unterminated string", 1),
+      ("a = 1 2", "Unexpected '2'", 1),
+      ("a = 01", "Invalid value '01'", 1),
+      ("a = 1__0", "Invalid value '1__0'", 1),
+      ("a =", "Unexpected end of input", 1),
+      ("a = 1979-05-27", "Invalid value '1979-05-27'", 1),
+      ("\n\n[x", "Unexpected end of input", 3),
+      ("a = [1]\n[[a]]", "Key 'a' is not an array of tables", 2),
+      (
+        "[[a]]\n[[a.b]]\n[[a]]\nb = [2]\n[[a.b]]",
+        "Key 'a.b' is not an array of tables",
+        5,
+      ),
+    ] {
+      let error = TOML::parse(input).unwrap_err();
+      assert_eq!(
+        (error.message.as_str(), error.line),
+        (message, line),
+        "{}",
+        input
+      );
+    }
+    assert_eq!(
+      TOML::parse("x = [1,").unwrap_err().to_string(),
+      "ERROR: Unexpected end of input in TOML at line 1, column 8"
+    );
+
+    for open in ["[", "{a="] {
+      let error = TOML::parse(&format!("x = {}", open.repeat(100_000))).unwrap_err();
+      assert_eq!(error.message, "Maximum nesting depth exceeded");
+      assert_eq!(error.kind, ErrorKind::DepthExceeded);
+      assert_eq!(error.column, 5 + 128 * open.len());
+    }
+    let nested = format!("x = {}{}", "[".repeat(128), "]".repeat(128));
+    assert!(TOML::parse(&nested).is_ok());
+  }
+}
+
+#[cfg(test)]
+mod ini_tests {
+  use ini::*;
+
+  use super::*;
+
+  #[test]
+  fn ini_parse_sections() {
+    let input = "; global settings\nname = demo\n\n[server] ; main\nhost = localhost  \nurl=http://x/?a=1#top\n\n[paths]\n  root = \"/var/www\" ; web root\n  motd = 'Hello \\n'\n\n[server]\nhost = 0.0.0.0\n";
+    assert_eq!(
+      INI::parse(input).unwrap(),
+      json!({
+        "name": "demo",
+        "server": {"host": "0.0.0.0", "url": "http://x/?a=1#top"},
+        "paths": {"root": "/var/www", "motd": "Hello \\n"}
+      })
+    );
+  }
+
+  #[test]
+  fn ini_parse_header_comment() {
+    assert_eq!(
+      INI::parse("[server]  # main\nhost = a\n[db];x]\n").unwrap(),
+      json!({"server": {"host": "a"}, "db": {}})
+    );
+  }
+
+  #[test]
+  fn ini_parse_flat() {
+    let input = "# .env\nexport API_KEY=abc=123\r\nGREETING=\"Hi\\tthere \
+                 \\\"you\\\"\"\nEMPTY=\nDEBUG=1\nDEBUG=0\n";
+    assert_eq!(
+      INI::parse(input).unwrap(),
+      json!({"API_KEY": "abc=123", "GREETING": "Hi\tthere \"you\"", "EMPTY": "", "DEBUG": "0"})
+    );
+    assert_eq!(INI::parse("").unwrap(), json!({}));
+  }
+
+  #[test]
+  fn ini_parse_errors() {
+    for (input, message, line, column) in [
+      ("a=1\njust text", "Expected '=' after key", 2, 1),
+      ("  = 1", "Missing key", 1, 3),
+      ("[open", "Unterminated section header", 1, 1),
+      ("a=1\n[a]", "Key 'a' is not a section", 2, 1),
+      ("[a]\n[]", "Empty section name", 2, 1),
+      ("[a] b", "Unexpected text after section header", 1, 5),
+      ("a = \"open", "Unterminated quoted value", 1, 5),
+      ("a = \"x\" y", "Unexpected text after closing quote", 1, 9),
+      ("a = \"\\q\"", "Invalid escape sequence", 1, 6),
+    ] {
+      let error = INI::parse(input).unwrap_err();
+      assert_eq!(
+        (error.message.as_str(), error.line, error.column),
+        (message, line, column),
+        "{}",
+        input
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod querystring_tests {
+  use querystring::*;
+
+  use super::*;
+
+  #[test]
+  fn querystring_parse() {
+    assert_eq!(
+      QueryString::parse("?a=1&b=2&&b=3&c&d=&b=4").unwrap(),
+      json!({"a": "1", "b": ["2", "3", "4"], "c": null, "d": ""})
+    );
+    assert_eq!(QueryString::parse("").unwrap(), json!({}));
+    assert_eq!(QueryString::parse("x=a=b").unwrap(), json!({"x": "a=b"}));
+  }
+
+  #[test]
+  fn querystring_parse_percent_encoding() {
+    assert_eq!(
+      QueryString::parse("q=hello+world%21&na%C3%AFve=%E2%9C%93&plus=%2B").unwrap(),
+      json!({"q": "hello world!", "na\u{ef}ve": "\u{2713}", "plus": "+"})
+    );
+    for (input, message, index) in [
+      ("a=%2", "Invalid percent-encoding", 2),
+      ("a=1&b%zz=2", "Invalid percent-encoding", 5),
+      ("a=%FF", "Percent-encoding is not valid UTF-8", 2),
+      ("a=1&b=x%C3y", "Percent-encoding is not valid UTF-8", 7),
+    ] {
+      let error = QueryString::parse(input).unwrap_err();
+      assert_eq!(
+        (error.message.as_str(), error.index),
+        (message, index),
+        "{}",
+        input
+      );
+    }
+    assert_eq!(
+      QueryString::parse("a=%").unwrap_err().to_string(),
+      "ERROR: Invalid percent-encoding in query string at index 2"
+    );
+  }
+}
+
+#[cfg(test)]
+mod percent_tests {
+  use super::*;
+
+  #[test]
+  fn percent_round_trip() {
+    for (input, encoded) in [
+      ("hello world", "hello%20world"),
+      ("a-b.c_d~e", "a-b.c_d~e"),
+      ("na\u{ef}ve \u{2713}", "na%C3%AFve%20%E2%9C%93"),
+      ("?a=1&b=/x#y+z%", "%3Fa%3D1%26b%3D%2Fx%23y%2Bz%25"),
+      ("", ""),
+    ] {
+      assert_eq!(percent::encode(input), encoded);
+      assert_eq!(percent::decode(encoded).unwrap(), input);
+    }
+    assert_eq!(percent::decode("a+b%2b").unwrap(), "a+b+");
+  }
+
+  #[test]
+  fn percent_decode_errors() {
+    for (input, message, index) in [
+      ("abc%", "Invalid percent-encoding", 3),
+      ("%4", "Invalid percent-encoding", 0),
+      ("ok%G1", "Invalid percent-encoding", 2),
+      ("%C3", "Percent-encoding is not valid UTF-8", 0),
+      ("ok%C3%28", "Percent-encoding is not valid UTF-8", 2),
+      (
+        "\u{e9}t\u{e9}%E2%9C",
+        "Percent-encoding is not valid UTF-8",
+        5,
+      ),
+      ("a%C3%A9b%FFc", "Percent-encoding is not valid UTF-8", 8),
+    ] {
+      let error = percent::decode(input).unwrap_err();
+      assert_eq!(
+        (error.message.as_str(), error.index),
+        (message, index),
+        "{}",
+        input
+      );
+    }
+    assert_eq!(
+      percent::decode("%C3").unwrap_err().kind,
+      ErrorKind::Encoding
+    );
+  }
+}
+
+#[cfg(test)]
+mod xml_tests {
+  use xml::*;
+
+  use super::*;
+
+  #[test]
+  fn xml_parse() {
+    let input = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- catalog -->
+<catalog version="2" lang='en'>
+  <book id="b1" available="true">
+    <title>Rust &amp; You</title>
+    <price currency="EUR">12.50</price>
+    <cover/>
+  </book>
+  <book id="b2">
+    <title><![CDATA[<Advanced> Topics]]></title>
+  </book>
+  Total: &#50;&#x33;
+</catalog>
+"#;
+    assert_eq!(
+      XML::parse(input).unwrap(),
+      json!({
+        "catalog": {
+          "@version": "2",
+          "@lang": "en",
+          "book": [
+            {
+              "@id": "b1",
+              "@available": "true",
+              "title": {"#text": "Rust & You"},
+              "price": {"@currency": "EUR", "#text": "12.50"},
+              "cover": {}
+            },
+            {"@id": "b2", "title": {"#text": "<Advanced> Topics"}}
+          ],
+          "#text": "Total: 23"
+        }
+      })
+    );
+  }
+
+  #[test]
+  fn xml_parse_attribute_and_child_with_one_name() {
+    assert_eq!(
+      XML::parse(r#"<e a="1"><a>2</a><a b="3"/></e>"#).unwrap(),
+      json!({"e": {"@a": "1", "a": [{"#text": "2"}, {"@b": "3"}]}})
+    );
+  }
+
+  #[test]
+  fn xml_parse_errors() {
+    for (input, message, line, column) in [
+      (
+        "<a>\n  <b></a>",
+        "Mismatched closing tag '</a>', expected '</b>'",
+        2,
+        6,
+      ),
+      ("<a>", "Unclosed element '<a>'", 1, 1),
+      ("<a x=\"1\" x=\"2\"/>", "Duplicate attribute 'x'", 1, 10),
+      ("<a>&nbsp;</a>", "Unknown entity '&nbsp;'", 1, 4),
+      (
+        "<a/><b/>",
+        "Unexpected content after the root element",
+        1,
+        5,
+      ),
+      ("text", "Unexpected 't'", 1, 1),
+      ("<a x=1/>", "Unexpected '1'", 1, 6),
+      ("<a x=\"1\"><@x>2</@x></a>", "Unexpected '@'", 1, 11),
+      ("<a><#text/></a>", "Unexpected '#'", 1, 5),
+      ("<a @x=\"1\"/>", "Unexpected '@'", 1, 4),
+      ("<1a/>", "Unexpected '1'", 1, 2),
+    ] {
+      let error = XML::parse(input).unwrap_err();
+      assert_eq!(
+        (error.message.as_str(), error.line, error.column),
+        (message, line, column),
+        "{}",
+        input
+      );
+    }
+
+    let error = XML::parse(&"<a>".repeat(100_000)).unwrap_err();
+    assert_eq!(error.message, "Maximum nesting depth exceeded");
+    assert_eq!(error.kind, ErrorKind::DepthExceeded);
+    assert_eq!(error.column, 3 * 128 + 1);
+    let nested = format!("{}{}", "<a>".repeat(128), "</a>".repeat(128));
+    assert!(XML::parse(&nested).is_ok());
+  }
+}
+
+#[cfg(test)]
+mod yaml_tests {
+  use yaml::*;
+
+  use super::*;
+
+  #[test]
+  fn yaml_parse_mapping() {
+    let input = "---\n# service config\nname: api   # inline comment\nversion: 2\nratio: 0.75\nenabled: true\nurl: http://host:80/#top\nempty:\nserver:\n  host: \"10.0.0.1\\t\"\n  ports: [80, 443]\n  limits:\n    cpu: '50%'\n    memory: ~\ntags:\n- a\n- 'b # not a comment'\n";
+    assert_eq!(
+      YAML::parse(input).unwrap(),
+      json!({
+        "name": "api",
+        "version": 2,
+        "ratio": 0.75,
+        "enabled": true,
+        "url": "http://host:80/#top",
+        "empty": null,
+        "server": {
+          "host": "10.0.0.1\t",
+          "ports": [80, 443],
+          "limits": {"cpu": "50%", "memory": null}
+        },
+        "tags": ["a", "b # not a comment"]
+      })
+    );
+    assert_eq!(YAML::parse("").unwrap(), json!(null));
+    assert_eq!(YAML::parse("hello: {a: 1, b: [x]}").unwrap_err().line, 1);
+  }
+
+  #[test]
+  fn yaml_parse_sequence_of_mappings() {
+    let input = "- name: Ann\n  age: 31\n  roles:\n    - admin\n    - dev\n-\n  name: Bob\n- - \
+                 1\n  - -2.5e3\n- \"quoted: no key\"\n";
+    assert_eq!(
+      YAML::parse(input).unwrap(),
+      json!([
+        {"name": "Ann", "age": 31, "roles": ["admin", "dev"]},
+        {"name": "Bob"},
+        [1, -2500.0],
+        "quoted: no key"
+      ])
+    );
+  }
+
+  #[test]
+  fn yaml_parse_errors() {
+    for (input, message, line) in [
+      ("a:\n    b: 1\n  c: 2", "Inconsistent indentation", 3),
+      ("a: 1\n  b: 2", "Inconsistent indentation", 2),
+      ("a: 1\na: 2", "Duplicate key 'a'", 2),
+      ("a: 1\n- b", "Inconsistent indentation", 2),
+      ("a:\n\t- b", "Tabs are not allowed in indentation", 2),
+      ("a: |\n  text", "Block scalars are not supported", 1),
+      ("a: 'open", "Unterminated string", 1),
+      ("a:\n  b: [1, 2", "Unexpected end of input", 2),
+    ] {
+      let error = YAML::parse(input).unwrap_err();
+      assert_eq!(
+        (error.message.as_str(), error.line),
+        (message, line),
+        "{}",
+        input
+      );
+    }
+
+    let deep = (0..1000)
+      .map(|i| format!("{}a:\n", " ".repeat(i)))
+      .collect::<String>();
+    let error = YAML::parse(&deep).unwrap_err();
+    assert_eq!(error.message, "Maximum nesting depth exceeded");
+    assert_eq!(error.kind, ErrorKind::DepthExceeded);
+    assert_eq!(error.line, 129);
+    let error = YAML::parse(&"- ".repeat(100_000)).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::DepthExceeded);
+    assert!(YAML::parse(&"- ".repeat(128)).is_ok());
+  }
+}
+
+#[cfg(test)]
+mod dispatch_tests {
+  use super::*;
+
+  #[test]
+  fn parse_dispatch() {
+    assert_eq!(
+      parse(r#"{"a": [1, 2]}"#, FileType::JSON).unwrap(),
+      json!({"a": [1, 2]})
+    );
+    assert_eq!(
+      parse("a,b\n1,2\n", FileType::CSV).unwrap(),
+      json!([{"a": "1", "b": "2"}])
+    );
+    assert_eq!(
+      parse("[t]\nx = 1", FileType::TOML).unwrap(),
+      json!({"t": {"x": 1}})
+    );
+    assert_eq!(
+      parse("[t]\nx = 1", FileType::INI).unwrap(),
+      json!({"t": {"x": "1"}})
+    );
+    assert_eq!(
+      parse("a+b%21", FileType::PercentEncoding).unwrap(),
+      json!("a+b!")
+    );
+    let error = parse("{", FileType::YAML).unwrap_err();
+    assert_eq!(error.filetype, FileType::YAML);
+  }
+
+  #[test]
+  fn parse_auto_detects_format() {
+    for (input, filetype, value) in [
+      (" {\"a\": 1}", FileType::JSON, json!({"a": 1})),
+      ("[1, 2]", FileType::JSON, json!([1, 2])),
+      (
+        "# app\n[server]\nport = 80\n",
+        FileType::TOML,
+        json!({"server": {"port": 80}}),
+      ),
+      (
+        "[server]\nhost = example.com\n",
+        FileType::INI,
+        json!({"server": {"host": "example.com"}}),
+      ),
+      ("name=demo", FileType::INI, json!({"name": "demo"})),
+      (
+        "name,age\nAnn,31\n",
+        FileType::CSV,
+        json!([{"name": "Ann", "age": "31"}]),
+      ),
+      (
+        "server:\n  port: 80\n",
+        FileType::YAML,
+        json!({"server": {"port": 80}}),
+      ),
+      ("- a\n- b", FileType::YAML, json!(["a", "b"])),
+      ("<a x=\"1\"/>", FileType::XML, json!({"a": {"@x": "1"}})),
+    ] {
+      assert_eq!(parse_auto(input).unwrap(), (filetype, value), "{}", input);
+    }
+    assert_eq!(
+      parse_auto("plain text").unwrap(),
+      (FileType::YAML, json!("plain text"))
+    );
+    assert_eq!(parse_auto("[section").unwrap_err().filetype, FileType::JSON);
+  }
 }
@@ -0,0 +1,57 @@
+//! Percent-encoding as used in URLs (RFC 3986).
+
+use std::fmt::Write;
+
+use super::{Error, ErrorKind};
+
+/// Percent-encodes every byte of `input` outside the unreserved set
+/// (`A-Z`, `a-z`, `0-9`, `-`, `.`, `_` and `~`), using uppercase hex digits.
+/// Non-ASCII characters are encoded byte by byte from their UTF-8 form.
+pub fn encode(input: &str) -> String {
+  let mut result = String::with_capacity(input.len());
+  for b in input.bytes() {
+    match b {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => result.push(b as char),
+      _ => write!(result, "%{:02X}", b).unwrap(),
+    }
+  }
+  result
+}
+
+/// Turns every `%XX` sequence in `input` back into the byte it stands for.
+/// Everything else, including `+`, is kept as is.
+///
+/// # Errors
+///
+/// Returns an Error if a `%` is not followed by two hex digits, or if the
+/// decoded bytes are not valid UTF-8. The latter points at the escape that
+/// starts the invalid byte sequence.
+pub fn decode(input: &str) -> Result<String, Error> {
+  let bytes = input.as_bytes();
+  let mut result = Vec::with_capacity(bytes.len());
+  // Where in `input` each byte of `result` comes from.
+  let mut offsets = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    offsets.push(i);
+    if bytes[i] == b'%' {
+      let byte = input
+        .get(i + 1..i + 3)
+        .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+        .map(|hex| u8::from_str_radix(hex, 16).unwrap());
+      match byte {
+        Some(byte) => result.push(byte),
+        None => return Err(Error::percent("Invalid percent-encoding".to_string(), i)),
+      }
+      i += 3;
+    } else {
+      result.push(bytes[i]);
+      i += 1;
+    }
+  }
+  String::from_utf8(result).map_err(|error| {
+    let index = offsets[error.utf8_error().valid_up_to()];
+    Error::percent("Percent-encoding is not valid UTF-8".to_string(), index)
+      .with_kind(ErrorKind::Encoding)
+  })
+}
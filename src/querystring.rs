@@ -0,0 +1,69 @@
+use super::{
+  json::{JsonMap, JsonValue},
+  percent, Error, FileType,
+};
+
+/// Decodes `+` and `%XX` sequences in `input`, which sits at byte offset
+/// `start` of the query string.
+fn decode_component(input: &str, start: usize) -> Result<String, Error> {
+  percent::decode(&input.replace('+', " ")).map_err(|error| Error {
+    filetype: FileType::QueryString,
+    index: start + error.index,
+    ..error
+  })
+}
+
+fn parse_pairs(input: &str) -> Result<JsonValue, Error> {
+  let skip = input.starts_with('?') as usize;
+  let mut result = JsonMap::new();
+  let mut offset = skip;
+  for pair in input[skip..].split('&') {
+    let start = offset;
+    offset += pair.len() + 1;
+    if pair.is_empty() {
+      continue;
+    }
+    let (key, value) = match pair.split_once('=') {
+      Some((key, value)) => {
+        let value_start = start + key.len() + 1;
+        (
+          key,
+          JsonValue::String(decode_component(value, value_start)?),
+        )
+      },
+      None => (pair, JsonValue::Null),
+    };
+    let key = decode_component(key, start)?;
+    match result.get_mut(&key) {
+      Some(JsonValue::Array(values)) => values.push(value),
+      Some(first) => *first = JsonValue::Array(vec![first.take(), value]),
+      None => {
+        result.insert(key, value);
+      },
+    }
+  }
+  Ok(JsonValue::Object(result))
+}
+
+/// Struct with methods for parsing URL query strings.
+pub struct QueryString {}
+
+impl QueryString {
+  /// Parses a query string such as `a=1&b=2&b=3&c` into a
+  /// [`JsonValue::Object`]. A leading `?` is skipped. Values are strings, a
+  /// key that appears more than once collects its values into a
+  /// [`JsonValue::Array`], and a bare key without `=` maps to
+  /// [`JsonValue::Null`], unlike `c=`, which maps to an empty string.
+  ///
+  /// Keys and values are percent-decoded, with `+` standing for a space.
+  ///
+  /// # Arguments
+  ///
+  /// - `input` - The query string to parse.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if a `%` is not followed by two hex digits, or if the
+  /// decoded bytes are not valid UTF-8.
+  pub fn parse(input: &str) -> Result<JsonValue, Error> { parse_pairs(input) }
+}
@@ -0,0 +1,650 @@
+use std::{collections::HashSet, fmt::Write};
+
+use super::{
+  json::{JsonMap, JsonValue, DEFAULT_MAX_DEPTH},
+  Error, ErrorKind,
+};
+
+/// Parser for the common subset of TOML. `index` is a byte offset into
+/// `toml` that always sits on a char boundary.
+struct TomlParser<'a> {
+  toml:         &'a str,
+  index:        usize,
+  root:         JsonMap,
+  /// Key path of the table that key/value pairs currently go into.
+  table:        Vec<String>,
+  /// Tables that were opened with a `[header]`, which may not be repeated.
+  defined:      HashSet<Vec<String>>,
+  /// Arrays that were created by a `[[header]]`, the only ones that later
+  /// `[[header]]`s may append to.
+  table_arrays: HashSet<Vec<String>>,
+  /// Number of arrays and inline tables currently open.
+  depth:        usize,
+}
+
+impl<'a> TomlParser<'a> {
+  pub fn new(toml: &'a str) -> Self {
+    Self {
+      toml,
+      index: 0,
+      root: JsonMap::new(),
+      table: vec![],
+      defined: HashSet::new(),
+      table_arrays: HashSet::new(),
+      depth: 0,
+    }
+  }
+
+  /// Opens an array or inline table whose bracket is the current char.
+  fn enter(&mut self) -> Result<(), Error> {
+    if self.depth >= DEFAULT_MAX_DEPTH {
+      return Err(
+        Error::toml("Maximum nesting depth exceeded".to_string(), self.index)
+          .with_kind(ErrorKind::DepthExceeded),
+      );
+    }
+    self.depth += 1;
+    Ok(())
+  }
+
+  fn current(&self) -> Option<char> { self.toml[self.index..].chars().next() }
+
+  fn peek(&self) -> Option<char> {
+    let mut chars = self.toml[self.index..].chars();
+    chars.next();
+    chars.next()
+  }
+
+  fn advance(&mut self) -> Option<char> {
+    if let Some(c) = self.current() {
+      self.index += c.len_utf8();
+    }
+    self.current()
+  }
+
+  fn expect(&mut self, c: char) -> Result<(), Error> {
+    if self.current() != Some(c) {
+      return Err(self.unexpected());
+    }
+    self.advance();
+    Ok(())
+  }
+
+  fn unexpected(&self) -> Error {
+    match self.current() {
+      Some('\n') => Error::toml("Unexpected end of line".to_string(), self.index),
+      Some(c) => Error::toml(format!("Unexpected '{}'", c), self.index),
+      None => Error::toml("Unexpected end of input".to_string(), self.index),
+    }
+  }
+
+  /// Skips spaces and tabs, and a comment up to the end of the line.
+  fn skip_space(&mut self) {
+    while matches!(self.current(), Some(' ' | '\t')) {
+      self.advance();
+    }
+    if self.current() == Some('#') {
+      while !matches!(self.current(), Some('\n') | None) {
+        self.advance();
+      }
+    }
+  }
+
+  /// Skips whitespace, comments and line breaks.
+  fn skip_lines(&mut self) {
+    loop {
+      self.skip_space();
+      match self.current() {
+        Some('\n') => self.advance(),
+        Some('\r') if self.peek() == Some('\n') => self.advance(),
+        _ => return,
+      };
+    }
+  }
+
+  /// Requires the rest of the line to be empty apart from a comment.
+  fn end_of_line(&mut self) -> Result<(), Error> {
+    self.skip_space();
+    match self.current() {
+      None | Some('\n') => Ok(()),
+      Some('\r') if self.peek() == Some('\n') => Ok(()),
+      _ => Err(self.unexpected()),
+    }
+  }
+
+  fn parse_key_part(&mut self) -> Result<String, Error> {
+    match self.current() {
+      Some('"') => self.parse_basic_string(),
+      Some('\'') => self.parse_literal_string(),
+      _ => {
+        let start = self.index;
+        while self
+          .current()
+          .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+          self.advance();
+        }
+        match start == self.index {
+          true => Err(self.unexpected()),
+          false => Ok(self.toml[start..self.index].to_string()),
+        }
+      },
+    }
+  }
+
+  /// Parses a possibly dotted key such as `a."b c".d`.
+  fn parse_key(&mut self) -> Result<Vec<String>, Error> {
+    let mut key = vec![];
+    loop {
+      self.skip_space();
+      key.push(self.parse_key_part()?);
+      self.skip_space();
+      if self.current() != Some('.') {
+        return Ok(key);
+      }
+      self.advance();
+    }
+  }
+
+  fn parse_basic_string(&mut self) -> Result<String, Error> {
+    let start = self.index;
+    let mut result = String::new();
+    loop {
+      match self.advance() {
+        Some('"') => {
+          self.advance();
+          return Ok(result);
+        },
+        Some('\\') => {
+          let escape = self.index;
+          let c = match self.advance() {
+            Some('b') => '\x08',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('f') => '\x0C',
+            Some('r') => '\r',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some(u @ ('u' | 'U')) => {
+              let len = if u == 'u' { 4 } else { 8 };
+              let digits = self.toml.get(self.index + 1..self.index + 1 + len);
+              let c = digits
+                .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|digits| char::from_u32(u32::from_str_radix(digits, 16).unwrap()));
+              match c {
+                Some(c) => {
+                  self.index += len;
+                  c
+                },
+                None => return Err(Error::toml("Invalid unicode escape".to_string(), escape)),
+              }
+            },
+            _ => return Err(Error::toml("Invalid escape sequence".to_string(), escape)),
+          };
+          result.push(c);
+        },
+        Some('\n') | None => return Err(Error::toml("Unterminated string".to_string(), start)),
+        Some(c) => result.push(c),
+      }
+    }
+  }
+
+  fn parse_literal_string(&mut self) -> Result<String, Error> {
+    let start = self.index;
+    loop {
+      match self.advance() {
+        Some('\'') => {
+          self.advance();
+          return Ok(self.toml[start + 1..self.index - 1].to_string());
+        },
+        Some('\n') | None => return Err(Error::toml("Unterminated string".to_string(), start)),
+        Some(_) => (),
+      }
+    }
+  }
+
+  fn parse_value(&mut self) -> Result<JsonValue, Error> {
+    match self.current() {
+      Some('"') if self.toml[self.index..].starts_with("\"\"\"") => Err(Error::toml(
+        "Multi-line strings are not supported".to_string(),
+        self.index,
+      )),
+      Some('"') => self.parse_basic_string().map(JsonValue::String),
+      Some('\'') => self.parse_literal_string().map(JsonValue::String),
+      Some('[') => self.parse_array(),
+      Some('{') => self.parse_inline_table(),
+      Some(_) => self.parse_scalar(),
+      None => Err(self.unexpected()),
+    }
+  }
+
+  /// Parses a boolean, integer or float.
+  fn parse_scalar(&mut self) -> Result<JsonValue, Error> {
+    let start = self.index;
+    while self
+      .current()
+      .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '.' | ':'))
+    {
+      self.advance();
+    }
+    let literal = &self.toml[start..self.index];
+    let invalid = || Error::toml(format!("Invalid value '{}'", literal), start);
+    match literal {
+      "" => Err(self.unexpected()),
+      "true" => Ok(JsonValue::Boolean(true)),
+      "false" => Ok(JsonValue::Boolean(false)),
+      "inf" | "+inf" => Ok(JsonValue::Number(f64::INFINITY)),
+      "-inf" => Ok(JsonValue::Number(f64::NEG_INFINITY)),
+      "nan" | "+nan" | "-nan" => Ok(JsonValue::Number(f64::NAN)),
+      _ => {
+        let bytes = literal.as_bytes();
+        // Underscores must sit between two digits.
+        for (i, b) in bytes.iter().enumerate() {
+          let digit =
+            |j: Option<usize>| j.and_then(|j| bytes.get(j)).is_some_and(u8::is_ascii_digit);
+          if *b == b'_' && !(digit(i.checked_sub(1)) && digit(Some(i + 1))) {
+            return Err(invalid());
+          }
+        }
+        let number = literal.replace('_', "");
+        let digits = number.trim_start_matches(['+', '-']);
+        if !digits.starts_with(|c: char| c.is_ascii_digit())
+          || digits.len() > 1 && digits.starts_with('0') && digits.as_bytes()[1].is_ascii_digit()
+        {
+          return Err(invalid());
+        }
+        if !number.contains(['.', 'e', 'E']) {
+          return number
+            .parse()
+            .map(JsonValue::Integer)
+            .map_err(|_| invalid());
+        }
+        if number.contains(".e") || number.contains(".E") || number.ends_with('.') {
+          return Err(invalid());
+        }
+        number.parse().map(JsonValue::Number).map_err(|_| invalid())
+      },
+    }
+  }
+
+  fn parse_array(&mut self) -> Result<JsonValue, Error> {
+    self.enter()?;
+    self.advance();
+    let mut result = vec![];
+    loop {
+      self.skip_lines();
+      if self.current() == Some(']') {
+        self.advance();
+        self.depth -= 1;
+        return Ok(JsonValue::Array(result));
+      }
+      result.push(self.parse_value()?);
+      self.skip_lines();
+      match self.current() {
+        Some(',') => {
+          self.advance();
+        },
+        Some(']') => (),
+        _ => return Err(self.unexpected()),
+      }
+    }
+  }
+
+  fn parse_inline_table(&mut self) -> Result<JsonValue, Error> {
+    self.enter()?;
+    self.advance();
+    let mut result = JsonMap::new();
+    self.skip_space();
+    if self.current() == Some('}') {
+      self.advance();
+      self.depth -= 1;
+      return Ok(JsonValue::Object(result));
+    }
+    loop {
+      let start = self.index;
+      let key = self.parse_key()?;
+      self.expect('=')?;
+      self.skip_space();
+      let value = self.parse_value()?;
+      insert_value(&mut result, &key, value, start)?;
+      self.skip_space();
+      match self.current() {
+        Some(',') => {
+          self.advance();
+          self.skip_space();
+        },
+        Some('}') => {
+          self.advance();
+          self.depth -= 1;
+          return Ok(JsonValue::Object(result));
+        },
+        _ => return Err(self.unexpected()),
+      }
+    }
+  }
+
+  /// Parses a `[table]` or `[[array of tables]]` header.
+  fn parse_header(&mut self) -> Result<(), Error> {
+    let start = self.index;
+    self.advance();
+    let array = self.current() == Some('[');
+    if array {
+      self.advance();
+    }
+    let key = self.parse_key()?;
+    self.expect(']')?;
+    if array {
+      self.expect(']')?;
+    }
+    self.end_of_line()?;
+    let (last, parent) = key.split_last().unwrap();
+    let parent = table_at(&mut self.root, parent, start)?;
+    let existing = parent.get_mut(last);
+    if array {
+      match existing {
+        Some(JsonValue::Array(tables)) if self.table_arrays.contains(&key) => {
+          tables.push(JsonValue::Object(JsonMap::new()))
+        },
+        Some(JsonValue::Array(_)) => {
+          return Err(Error::toml(
+            format!("Key '{}' is not an array of tables", key.join(".")),
+            start,
+          ))
+        },
+        Some(_) => return Err(defined_twice(&key, start)),
+        None => {
+          parent.insert(last.clone(), vec![JsonValue::Object(JsonMap::new())].into());
+        },
+      }
+      // The new element starts with none of its subtables defined.
+      self.defined.retain(|defined| !defined.starts_with(&key));
+      self
+        .table_arrays
+        .retain(|array| array == &key || !array.starts_with(&key));
+      self.table_arrays.insert(key.clone());
+    } else {
+      match existing {
+        Some(JsonValue::Object(_)) if !self.defined.contains(&key) => (),
+        Some(JsonValue::Object(_)) => return Err(defined_twice(&key, start)),
+        Some(_) => {
+          return Err(Error::toml(
+            format!("Key '{}' is not a table", key.join(".")),
+            start,
+          ))
+        },
+        None => {
+          parent.insert(last.clone(), JsonValue::Object(JsonMap::new()));
+        },
+      }
+      self.defined.insert(key.clone());
+    }
+    self.table = key;
+    Ok(())
+  }
+
+  pub fn parse(mut self) -> Result<JsonValue, Error> {
+    let toml = self.toml;
+    self.parse_document().map_err(|error| error.locate(toml))
+  }
+
+  fn parse_document(&mut self) -> Result<JsonValue, Error> {
+    loop {
+      self.skip_lines();
+      match self.current() {
+        None => return Ok(JsonValue::Object(std::mem::take(&mut self.root))),
+        Some('[') => self.parse_header()?,
+        Some(_) => {
+          let start = self.index;
+          let key = self.parse_key()?;
+          self.expect('=')?;
+          self.skip_space();
+          let value = self.parse_value()?;
+          self.end_of_line()?;
+          let table = table_at(&mut self.root, &self.table, start)?;
+          insert_value(table, &key, value, start)?;
+        },
+      }
+    }
+  }
+}
+
+fn defined_twice(key: &[String], pos: usize) -> Error {
+  Error::toml(format!("Table '{}' is defined twice", key.join(".")), pos)
+}
+
+/// Walks `path` down from `root`, creating missing tables. A step into an
+/// array of tables continues in its last table.
+fn table_at<'v>(
+  mut table: &'v mut JsonMap,
+  path: &[String],
+  pos: usize,
+) -> Result<&'v mut JsonMap, Error> {
+  for key in path {
+    if !table.contains_key(key) {
+      table.insert(key.clone(), JsonValue::Object(JsonMap::new()));
+    }
+    table = match table.get_mut(key) {
+      Some(JsonValue::Object(next)) => next,
+      Some(JsonValue::Array(tables)) => match tables.last_mut() {
+        Some(JsonValue::Object(next)) => next,
+        _ => return Err(Error::toml(format!("Key '{}' is not a table", key), pos)),
+      },
+      _ => return Err(Error::toml(format!("Key '{}' is not a table", key), pos)),
+    };
+  }
+  Ok(table)
+}
+
+/// Inserts `value` under a possibly dotted `key`, creating intermediate
+/// tables.
+fn insert_value(
+  table: &mut JsonMap,
+  key: &[String],
+  value: JsonValue,
+  pos: usize,
+) -> Result<(), Error> {
+  let (last, parent) = key.split_last().unwrap();
+  let table = table_at(table, parent, pos)?;
+  if table.contains_key(last) {
+    return Err(Error::toml(
+      format!("Duplicate key '{}'", key.join(".")),
+      pos,
+    ));
+  }
+  table.insert(last.clone(), value);
+  Ok(())
+}
+
+fn write_toml_key(key: &str, out: &mut String) {
+  let bare = key
+    .bytes()
+    .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+  match bare && !key.is_empty() {
+    true => out.push_str(key),
+    false => write_toml_string(key, out),
+  }
+}
+
+fn write_toml_string(s: &str, out: &mut String) {
+  out.push('"');
+  for c in s.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\x08' => out.push_str("\\b"),
+      '\t' => out.push_str("\\t"),
+      '\n' => out.push_str("\\n"),
+      '\x0C' => out.push_str("\\f"),
+      '\r' => out.push_str("\\r"),
+      c if c.is_control() => write!(out, "\\u{:04X}", c as u32).unwrap(),
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+}
+
+/// Whether `values` is written as an `[[array of tables]]`. An array that
+/// mixes tables with other values has no TOML form.
+fn is_table_array(values: &[JsonValue], path: &str) -> Result<bool, Error> {
+  let tables = values.iter().filter(|value| value.is_object()).count();
+  match tables {
+    0 => Ok(false),
+    _ if tables == values.len() => Ok(true),
+    _ => Err(Error::toml(
+      format!("Array at '{}' mixes tables and other values", path),
+      0,
+    )),
+  }
+}
+
+/// Writes `value` as an inline value, with `path` naming it in errors.
+fn write_toml_value(value: &JsonValue, path: &str, out: &mut String) -> Result<(), Error> {
+  match value {
+    JsonValue::Null => {
+      return Err(Error::toml(
+        format!("Null at '{}' has no TOML form", path),
+        0,
+      ))
+    },
+    JsonValue::Boolean(b) => write!(out, "{}", b).unwrap(),
+    JsonValue::Integer(i) => write!(out, "{}", i).unwrap(),
+    JsonValue::RawNumber(raw) => out.push_str(raw),
+    JsonValue::Number(n) if n.is_nan() => out.push_str("nan"),
+    JsonValue::Number(n) if n.is_infinite() => out.push_str(if *n > 0.0 { "inf" } else { "-inf" }),
+    // Debug keeps a `.0` or an exponent, so the number stays a float.
+    JsonValue::Number(n) => write!(out, "{:?}", n).unwrap(),
+    JsonValue::String(s) => write_toml_string(s, out),
+    JsonValue::Array(values) => {
+      out.push('[');
+      for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+          out.push_str(", ");
+        }
+        write_toml_value(value, &format!("{}[{}]", path, i), out)?;
+      }
+      out.push(']');
+    },
+    JsonValue::Object(map) => {
+      out.push('{');
+      for (i, (key, value)) in map.iter().enumerate() {
+        out.push_str(if i > 0 { ", " } else { " " });
+        write_toml_key(key, out);
+        out.push_str(" = ");
+        write_toml_value(value, &format!("{}.{}", path, key), out)?;
+      }
+      out.push_str(if map.is_empty() { "}" } else { " }" });
+    },
+  }
+  Ok(())
+}
+
+/// Writes the key/value pairs of `map` followed by its subtables, whose
+/// headers start with `header`, a dotted key of already written keys.
+fn write_toml_table(
+  map: &JsonMap,
+  header: &str,
+  path: &str,
+  out: &mut String,
+) -> Result<(), Error> {
+  let join = |prefix: &str, key: &str| match prefix.is_empty() {
+    true => key.to_string(),
+    false => format!("{}.{}", prefix, key),
+  };
+  for (key, value) in map {
+    let inline = match value {
+      JsonValue::Object(_) => false,
+      JsonValue::Array(values) => !is_table_array(values, &join(path, key))?,
+      _ => true,
+    };
+    if inline {
+      write_toml_key(key, out);
+      out.push_str(" = ");
+      write_toml_value(value, &join(path, key), out)?;
+      out.push('\n');
+    }
+  }
+  for (key, value) in map {
+    let mut subheader = String::new();
+    write_toml_key(key, &mut subheader);
+    let subheader = join(header, &subheader);
+    match value {
+      JsonValue::Object(table) => {
+        if !out.is_empty() {
+          out.push('\n');
+        }
+        writeln!(out, "[{}]", subheader).unwrap();
+        write_toml_table(table, &subheader, &join(path, key), out)?;
+      },
+      JsonValue::Array(tables) if is_table_array(tables, &join(path, key))? => {
+        for (i, table) in tables.iter().enumerate() {
+          if !out.is_empty() {
+            out.push('\n');
+          }
+          writeln!(out, "[[{}]]", subheader).unwrap();
+          let table = table.as_object().unwrap();
+          write_toml_table(
+            table,
+            &subheader,
+            &format!("{}[{}]", join(path, key), i),
+            out,
+          )?;
+        }
+      },
+      _ => (),
+    }
+  }
+  Ok(())
+}
+
+/// Struct with methods for parsing TOML configuration files.
+pub struct TOML {}
+
+impl TOML {
+  /// Parses a TOML string into a [`JsonValue::Object`]. Tables become
+  /// objects, arrays of tables become arrays of objects, integers become
+  /// [`JsonValue::Integer`] and floats [`JsonValue::Number`].
+  ///
+  /// The common subset of TOML is supported: key/value pairs with bare,
+  /// quoted and dotted keys, `[table]` and `[[array of tables]]` headers,
+  /// basic and literal strings, integers, floats, booleans, arrays, inline
+  /// tables and `#` comments. Multi-line strings and dates are not.
+  ///
+  /// # Arguments
+  ///
+  /// - `toml` - The TOML string to parse.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if the TOML string is invalid or uses an unsupported
+  /// feature, if a key or table is defined twice, or if arrays and inline
+  /// tables are nested more than 128 deep.
+  pub fn parse(toml: &str) -> Result<JsonValue, Error> { TomlParser::new(toml).parse() }
+
+  /// Serializes a [`JsonValue::Object`] into TOML. Values other than
+  /// objects and arrays of objects are written first, as `key = value`
+  /// lines. Nested objects follow as `[table]` sections and arrays of
+  /// objects as `[[array of tables]]` sections; inside inline values,
+  /// objects are written as inline tables.
+  ///
+  /// # Arguments
+  ///
+  /// - `value` - The object to serialize.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if `value` is not an object, if it contains a null,
+  /// which TOML has no form for, or if an array mixes objects with other
+  /// values where it would need to become an array of tables. The error's
+  /// message names the offending key.
+  pub fn stringify(value: &JsonValue) -> Result<String, Error> {
+    let Some(map) = value.as_object() else {
+      return Err(Error::toml(
+        "Only an object can be written as TOML".to_string(),
+        0,
+      ));
+    };
+    let mut out = String::new();
+    write_toml_table(map, "", "", &mut out)?;
+    Ok(out)
+  }
+}
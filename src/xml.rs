@@ -0,0 +1,287 @@
+use super::{
+  json::{JsonMap, JsonValue, DEFAULT_MAX_DEPTH},
+  Error, ErrorKind,
+};
+
+/// Parser for well-formed XML documents. `index` is a byte offset into
+/// `xml` that always sits on a char boundary, and `depth` counts the
+/// elements currently open.
+struct XmlParser<'a> {
+  xml:   &'a str,
+  index: usize,
+  depth: usize,
+}
+
+impl<'a> XmlParser<'a> {
+  pub fn new(xml: &'a str) -> Self {
+    Self {
+      xml,
+      index: 0,
+      depth: 0,
+    }
+  }
+
+  fn rest(&self) -> &'a str { &self.xml[self.index..] }
+
+  fn current(&self) -> Option<char> { self.rest().chars().next() }
+
+  fn unexpected(&self) -> Error {
+    match self.current() {
+      Some(c) => Error::xml(format!("Unexpected '{}'", c), self.index),
+      None => Error::xml("Unexpected end of input".to_string(), self.index),
+    }
+  }
+
+  fn expect(&mut self, c: char) -> Result<(), Error> {
+    if self.current() != Some(c) {
+      return Err(self.unexpected());
+    }
+    self.index += c.len_utf8();
+    Ok(())
+  }
+
+  fn skip_whitespace(&mut self) { self.index = self.xml.len() - self.rest().trim_start().len(); }
+
+  /// Skips past the next `end`, which closes a construct opened at `start`.
+  fn skip_past(&mut self, end: &str, what: &str, start: usize) -> Result<&'a str, Error> {
+    match self.rest().find(end) {
+      Some(len) => {
+        let skipped = &self.rest()[..len];
+        self.index += len + end.len();
+        Ok(skipped)
+      },
+      None => Err(Error::xml(format!("Unterminated {}", what), start)),
+    }
+  }
+
+  /// Skips comments, processing instructions (including the XML
+  /// declaration), a doctype and whitespace. Returns whether anything was
+  /// skipped.
+  fn skip_misc(&mut self) -> Result<bool, Error> {
+    let start = self.index;
+    if self.rest().starts_with("<!--") {
+      self.skip_past("-->", "comment", start)?;
+    } else if self.rest().starts_with("<?") {
+      self.skip_past("?>", "processing instruction", start)?;
+    } else if self.rest().starts_with("<!DOCTYPE") {
+      self.skip_past(">", "doctype", start)?;
+    } else if self.current().is_some_and(char::is_whitespace) {
+      self.skip_whitespace();
+    } else {
+      return Ok(false);
+    }
+    Ok(true)
+  }
+
+  fn parse_name(&mut self) -> Result<&'a str, Error> {
+    let len = self
+      .rest()
+      .find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '=' | '<' | '"' | '\''))
+      .unwrap_or(self.rest().len());
+    // XML names cannot start with these, which keeps element names apart
+    // from the `@` keys of attributes and the `#text` key.
+    let invalid_start = |c: char| c.is_ascii_digit() || matches!(c, '@' | '#' | '-' | '.');
+    if len == 0 || self.rest().starts_with(invalid_start) {
+      return Err(self.unexpected());
+    }
+    let name = &self.rest()[..len];
+    self.index += len;
+    Ok(name)
+  }
+
+  /// Replaces entity and character references in `text`, which sits at
+  /// byte offset `start` of the input.
+  fn decode_text(&self, text: &str, start: usize) -> Result<String, Error> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+      result.push_str(&rest[..amp]);
+      let pos = start + (text.len() - rest.len()) + amp;
+      let Some(len) = rest[amp..].find(';') else {
+        return Err(Error::xml("Unterminated entity reference".to_string(), pos));
+      };
+      let entity = &rest[amp + 1..amp + len];
+      let c = match entity {
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "amp" => Some('&'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => match entity
+          .strip_prefix("#x")
+          .or_else(|| entity.strip_prefix("#X"))
+        {
+          Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+          None => entity
+            .strip_prefix('#')
+            .and_then(|decimal| decimal.parse().ok())
+            .and_then(char::from_u32),
+        },
+      };
+      match c {
+        Some(c) => result.push(c),
+        None => return Err(Error::xml(format!("Unknown entity '&{};'", entity), pos)),
+      }
+      rest = &rest[amp + len + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+  }
+
+  fn parse_attributes(&mut self, element: &mut JsonMap) -> Result<(), Error> {
+    loop {
+      self.skip_whitespace();
+      if matches!(self.current(), Some('/' | '>') | None) {
+        return Ok(());
+      }
+      let start = self.index;
+      let key = format!("@{}", self.parse_name()?);
+      self.skip_whitespace();
+      self.expect('=')?;
+      self.skip_whitespace();
+      let quote = match self.current() {
+        Some(quote @ ('"' | '\'')) => quote,
+        _ => return Err(self.unexpected()),
+      };
+      let value_start = self.index + 1;
+      self.index = value_start;
+      let value = self.skip_past(&quote.to_string(), "attribute value", value_start - 1)?;
+      if element.contains_key(&key) {
+        return Err(Error::xml(
+          format!("Duplicate attribute '{}'", &key[1..]),
+          start,
+        ));
+      }
+      element.insert(
+        key,
+        JsonValue::String(self.decode_text(value, value_start)?),
+      );
+    }
+  }
+
+  /// Parses an element whose `<` is at `index`. Returns its name and value.
+  fn parse_element(&mut self) -> Result<(&'a str, JsonValue), Error> {
+    if self.depth >= DEFAULT_MAX_DEPTH {
+      return Err(
+        Error::xml("Maximum nesting depth exceeded".to_string(), self.index)
+          .with_kind(ErrorKind::DepthExceeded),
+      );
+    }
+    self.depth += 1;
+    let result = self.parse_element_body();
+    self.depth -= 1;
+    result
+  }
+
+  fn parse_element_body(&mut self) -> Result<(&'a str, JsonValue), Error> {
+    let start = self.index;
+    self.expect('<')?;
+    let name = self.parse_name()?;
+    let mut element = JsonMap::new();
+    self.parse_attributes(&mut element)?;
+    if self.rest().starts_with("/>") {
+      self.index += 2;
+      return Ok((name, JsonValue::Object(element)));
+    }
+    self.expect('>')?;
+    let mut text = String::new();
+    loop {
+      let text_start = self.index;
+      let len = self.rest().find('<').unwrap_or(self.rest().len());
+      text.push_str(&self.decode_text(&self.rest()[..len], text_start)?);
+      self.index += len;
+      let tag = self.index;
+      if self.rest().starts_with("</") {
+        self.index += 2;
+        let closing = self.parse_name()?;
+        if closing != name {
+          return Err(Error::xml(
+            format!(
+              "Mismatched closing tag '</{}>', expected '</{}>'",
+              closing, name
+            ),
+            tag,
+          ));
+        }
+        self.skip_whitespace();
+        self.expect('>')?;
+        break;
+      } else if self.rest().starts_with("<![CDATA[") {
+        self.index += "<![CDATA[".len();
+        text.push_str(self.skip_past("]]>", "CDATA section", tag)?);
+      } else if self.rest().starts_with("<!--") || self.rest().starts_with("<?") {
+        self.skip_misc()?;
+      } else if self.current().is_none() {
+        return Err(Error::xml(format!("Unclosed element '<{}>'", name), start));
+      } else {
+        let (child, value) = self.parse_element()?;
+        match element.get_mut(child) {
+          Some(JsonValue::Array(values)) => values.push(value),
+          Some(first) => *first = JsonValue::Array(vec![first.take(), value]),
+          None => {
+            element.insert(child.to_string(), value);
+          },
+        }
+      }
+    }
+    let text = text.trim();
+    if !text.is_empty() {
+      element.insert("#text".to_string(), JsonValue::String(text.to_string()));
+    }
+    Ok((name, JsonValue::Object(element)))
+  }
+
+  pub fn parse(mut self) -> Result<JsonValue, Error> {
+    let xml = self.xml;
+    self.parse_document().map_err(|error| error.locate(xml))
+  }
+
+  fn parse_document(&mut self) -> Result<JsonValue, Error> {
+    while self.skip_misc()? {}
+    if self.current() != Some('<') {
+      return Err(self.unexpected());
+    }
+    let (name, root) = self.parse_element()?;
+    while self.skip_misc()? {}
+    if self.current().is_some() {
+      return Err(Error::xml(
+        "Unexpected content after the root element".to_string(),
+        self.index,
+      ));
+    }
+    let mut result = JsonMap::new();
+    result.insert(name.to_string(), root);
+    Ok(JsonValue::Object(result))
+  }
+}
+
+/// Struct with methods for parsing XML documents.
+pub struct XML {}
+
+impl XML {
+  /// Parses an XML document into a [`JsonValue::Object`] with the root
+  /// element as its only key.
+  ///
+  /// Every element becomes an object. Attributes are stored under their
+  /// name prefixed with `@`, child elements under their name (as an array
+  /// when the name repeats), and the element's text, trimmed and with
+  /// references such as `&amp;` resolved, under `#text` unless it is
+  /// empty. CDATA sections count as text. Comments, processing
+  /// instructions and the doctype are skipped.
+  ///
+  /// These keys never collide: an attribute `a` and a child element `<a>`
+  /// are stored side by side as `@a` and `a`, and names starting with `@`
+  /// or `#`, which XML does not allow, are rejected.
+  ///
+  /// # Arguments
+  ///
+  /// - `input` - The XML string to parse.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if the document is not well-formed, e.g. if a closing
+  /// tag does not match the open element, if a name starts with a character
+  /// XML does not allow there, if it uses an unknown entity,
+  /// or if elements are nested more than 128 deep.
+  pub fn parse(input: &str) -> Result<JsonValue, Error> { XmlParser::new(input).parse() }
+}
@@ -0,0 +1,280 @@
+use super::{
+  json::{JsonMap, JsonValue, ParseOptions, DEFAULT_MAX_DEPTH, JSON},
+  Error, ErrorKind, FileType,
+};
+
+/// A non-blank line with its comment removed.
+#[derive(Clone, Copy)]
+struct Line<'a> {
+  indent: usize,
+  text:   &'a str,
+  /// Byte offset of `text` in the input.
+  pos:    usize,
+}
+
+/// Strips a `#` comment, which has to start the text or follow whitespace,
+/// from outside of quotes.
+fn strip_comment(text: &str) -> &str {
+  let mut quote = None;
+  let mut previous = ' ';
+  for (i, c) in text.char_indices() {
+    match (quote, c) {
+      (None, '#') if previous.is_whitespace() => return text[..i].trim_end(),
+      (None, '"' | '\'') => quote = Some(c),
+      (Some('"'), '\\') => (),
+      (Some(q), c) if q == c && previous != '\\' => quote = None,
+      _ => (),
+    }
+    previous = c;
+  }
+  text.trim_end()
+}
+
+fn split_lines(input: &str) -> Result<Vec<Line<'_>>, Error> {
+  let mut lines = vec![];
+  let mut offset = 0;
+  for raw in input.split_inclusive('\n') {
+    let start = offset;
+    offset += raw.len();
+    let indent = raw.len() - raw.trim_start_matches(' ').len();
+    let text = strip_comment(&raw[indent..]);
+    if text.is_empty() || text == "---" || text == "..." {
+      continue;
+    }
+    if text.starts_with('\t') {
+      return Err(Error::yaml(
+        "Tabs are not allowed in indentation".to_string(),
+        start + indent,
+      ));
+    }
+    lines.push(Line {
+      indent,
+      text,
+      pos: start + indent,
+    });
+  }
+  Ok(lines)
+}
+
+fn is_item(text: &str) -> bool { text == "-" || text.starts_with("- ") }
+
+/// Splits `key: value` at the first colon that is followed by a space or
+/// ends the line, looking past a quoted key. Returns `None` for a scalar.
+fn split_key(text: &str) -> Option<(&str, &str)> {
+  let key_end = match text.chars().next() {
+    Some(quote @ ('"' | '\'')) => text[1..].find(quote)? + 2,
+    _ => 0,
+  };
+  let colon = text[key_end..]
+    .match_indices(':')
+    .map(|(i, _)| key_end + i)
+    .find(|&i| text[i + 1..].is_empty() || text[i + 1..].starts_with(' '))?;
+  Some((text[..colon].trim_end(), text[colon + 1..].trim_start()))
+}
+
+/// Rebrands an error from the JSON parser for text at `pos` of the input.
+fn from_json(error: Error, pos: usize) -> Error {
+  Error {
+    filetype: FileType::YAML,
+    index: pos + error.index,
+    ..error
+  }
+}
+
+/// Parses an inline value: a quoted or plain scalar, or a flow collection.
+fn parse_scalar(text: &str, pos: usize) -> Result<JsonValue, Error> {
+  if text.starts_with('"') {
+    return JSON::parse(text).map_err(|error| from_json(error, pos));
+  }
+  if text.starts_with(['[', '{']) {
+    return JSON::parse_with(text, ParseOptions::json5()).map_err(|error| from_json(error, pos));
+  }
+  if let Some(quoted) = text.strip_prefix('\'') {
+    return match quoted.strip_suffix('\'') {
+      Some(quoted) => Ok(JsonValue::String(quoted.replace("''", "'"))),
+      None => Err(Error::yaml("Unterminated string".to_string(), pos)),
+    };
+  }
+  if matches!(text.trim_end_matches(['-', '+']), "|" | ">") {
+    return Err(Error::yaml(
+      "Block scalars are not supported".to_string(),
+      pos,
+    ));
+  }
+  let is_number = |text: &str| {
+    text.bytes().any(|b| b.is_ascii_digit())
+      && text
+        .bytes()
+        .all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'+' | b'-'))
+  };
+  Ok(match text {
+    "~" | "null" | "Null" | "NULL" => JsonValue::Null,
+    "true" | "True" | "TRUE" => JsonValue::Boolean(true),
+    "false" | "False" | "FALSE" => JsonValue::Boolean(false),
+    ".inf" | ".Inf" | ".INF" | "+.inf" | "+.Inf" | "+.INF" => JsonValue::Number(f64::INFINITY),
+    "-.inf" | "-.Inf" | "-.INF" => JsonValue::Number(f64::NEG_INFINITY),
+    ".nan" | ".NaN" | ".NAN" => JsonValue::Number(f64::NAN),
+    _ => match (text.parse::<i64>(), text.parse::<f64>()) {
+      (Ok(integer), _) => JsonValue::Integer(integer),
+      (_, Ok(number)) if is_number(text) => JsonValue::Number(number),
+      _ => JsonValue::String(text.to_string()),
+    },
+  })
+}
+
+/// Parser for block-style YAML working on the document's lines. `depth`
+/// counts the block collections currently open.
+struct YamlParser<'a> {
+  lines: Vec<Line<'a>>,
+  index: usize,
+  depth: usize,
+}
+
+impl<'a> YamlParser<'a> {
+  /// Opens a block collection starting at the current line.
+  fn enter(&mut self) -> Result<(), Error> {
+    if self.depth >= DEFAULT_MAX_DEPTH {
+      return Err(
+        Error::yaml(
+          "Maximum nesting depth exceeded".to_string(),
+          self.lines[self.index].pos,
+        )
+        .with_kind(ErrorKind::DepthExceeded),
+      );
+    }
+    self.depth += 1;
+    Ok(())
+  }
+
+  /// Parses the node whose first line is the current one.
+  fn parse_node(&mut self) -> Result<JsonValue, Error> {
+    let line = self.lines[self.index];
+    if is_item(line.text) {
+      return self.parse_sequence(line.indent);
+    }
+    if split_key(line.text).is_some() {
+      return self.parse_mapping(line.indent);
+    }
+    self.index += 1;
+    parse_scalar(line.text, line.pos)
+  }
+
+  /// Parses the value of a `key:` or `-` without inline content, which is
+  /// indented deeper than `indent` or, for a key, a sequence at the same
+  /// indentation. Anything else makes it null.
+  fn parse_nested(&mut self, indent: usize, is_key: bool) -> Result<JsonValue, Error> {
+    match self.lines.get(self.index) {
+      Some(line) if line.indent > indent => self.parse_node(),
+      Some(line) if is_key && line.indent == indent && is_item(line.text) => {
+        self.parse_sequence(indent)
+      },
+      _ => Ok(JsonValue::Null),
+    }
+  }
+
+  fn parse_sequence(&mut self, indent: usize) -> Result<JsonValue, Error> {
+    self.enter()?;
+    let mut items = vec![];
+    while let Some(line) = self.lines.get_mut(self.index) {
+      if line.indent != indent || !is_item(line.text) {
+        break;
+      }
+      let content = line.text[1..].trim_start();
+      if content.is_empty() {
+        self.index += 1;
+        items.push(self.parse_nested(indent, false)?);
+        continue;
+      }
+      // Whatever follows the dash is parsed as if it started its own line,
+      // so that `- key: value` opens a mapping at the key's column.
+      let offset = line.text.len() - content.len();
+      *line = Line {
+        indent: indent + offset,
+        text:   content,
+        pos:    line.pos + offset,
+      };
+      items.push(self.parse_node()?);
+    }
+    self.depth -= 1;
+    Ok(JsonValue::Array(items))
+  }
+
+  fn parse_mapping(&mut self, indent: usize) -> Result<JsonValue, Error> {
+    self.enter()?;
+    let mut map = JsonMap::new();
+    while let Some(&line) = self.lines.get(self.index) {
+      if line.indent != indent || is_item(line.text) {
+        break;
+      }
+      let Some((key, value)) = split_key(line.text) else {
+        return Err(Error::yaml("Expected 'key: value'".to_string(), line.pos));
+      };
+      let key = match key.starts_with(['"', '\'']) {
+        true => parse_scalar(key, line.pos)?.as_str().unwrap().to_string(),
+        false => key.to_string(),
+      };
+      self.index += 1;
+      let value = match value.is_empty() {
+        true => self.parse_nested(indent, true)?,
+        false => parse_scalar(value, line.pos + (line.text.len() - value.len()))?,
+      };
+      if map.contains_key(&key) {
+        return Err(Error::yaml(format!("Duplicate key '{}'", key), line.pos));
+      }
+      map.insert(key, value);
+    }
+    self.depth -= 1;
+    Ok(JsonValue::Object(map))
+  }
+
+  fn parse_document(input: &'a str) -> Result<JsonValue, Error> {
+    let mut parser = Self {
+      lines: split_lines(input)?,
+      index: 0,
+      depth: 0,
+    };
+    if parser.lines.is_empty() {
+      return Ok(JsonValue::Null);
+    }
+    let value = parser.parse_node()?;
+    // Every block stops at a line it does not own, so a line left over is
+    // one that fits no open block.
+    match parser.lines.get(parser.index) {
+      Some(line) => Err(Error::yaml(
+        "Inconsistent indentation".to_string(),
+        line.pos,
+      )),
+      None => Ok(value),
+    }
+  }
+}
+
+/// Struct with methods for parsing YAML documents.
+pub struct YAML {}
+
+impl YAML {
+  /// Parses a YAML document into a [`JsonValue`]. An empty document is
+  /// [`JsonValue::Null`].
+  ///
+  /// A practical subset of YAML is supported: block mappings and block
+  /// sequences nested by indentation, plain, single- and double-quoted
+  /// scalars, `#` comments and a leading `---`. Plain scalars become null,
+  /// booleans, integers or floats where YAML's core schema says so and
+  /// strings otherwise. Flow collections (`[...]`, `{...}`) must fit on one
+  /// line and are read by the JSON parser with [`ParseOptions::json5`].
+  /// Multi-line plain scalars, block scalars (`|`, `>`), anchors, tags and
+  /// multiple documents are not supported.
+  ///
+  /// # Arguments
+  ///
+  /// - `input` - The YAML string to parse.
+  ///
+  /// # Errors
+  ///
+  /// Returns an Error if a line does not fit the indentation of the block
+  /// it is in, if a mapping repeats a key, if a scalar or flow collection
+  /// is malformed, or if block collections are nested more than 128 deep.
+  pub fn parse(input: &str) -> Result<JsonValue, Error> {
+    YamlParser::parse_document(input).map_err(|error| error.locate(input))
+  }
+}